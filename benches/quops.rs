@@ -2,20 +2,20 @@ use criterion::{criterion_group, criterion_main, Criterion};
 use std::hint::black_box;
 
 #[derive(bitcode::Encode, bitcode::Decode, quops::Encode, quops::Decode, Debug)]
-#[schema(path = "/home/adrian/Programming/JavaScript/QUOP/quop/shared/src/quops/GameMode.quops")]
+#[schema(path = "./schemas/GameMode.quops")]
 enum GameMode {
     Normal,
 }
 
 #[derive(bitcode::Encode, bitcode::Decode, quops::Encode, quops::Decode, Debug)]
-#[schema(path = "/home/adrian/Programming/JavaScript/QUOP/quop/shared/src/quops/Language.quops")]
+#[schema(path = "./schemas/Language.quops")]
 enum Language {
     English,
     French,
 }
 
 #[derive(bitcode::Encode, bitcode::Decode, quops::Encode, quops::Decode, Debug)]
-#[schema(path = "/home/adrian/Programming/JavaScript/QUOP/quop/shared/src/quops/RegenChallengeDifficulty.quops")]
+#[schema(path = "./schemas/RegenChallengeDifficulty.quops")]
 enum RegenChallengeDifficulty {
     Easy,
     Medium,
@@ -23,7 +23,7 @@ enum RegenChallengeDifficulty {
 }
 
 #[derive(bitcode::Encode, bitcode::Decode, quops::Encode, quops::Decode, Debug)]
-#[schema(path = "/home/adrian/Programming/JavaScript/QUOP/quop/shared/src/quops/ScratchphraseRules.quops")]
+#[schema(path = "./schemas/ScratchphraseRules.quops")]
 struct ScratchphraseRules {
     language: Language,
     game_mode: GameMode,
@@ -38,7 +38,8 @@ struct ScratchphraseRules {
 }
 
 #[derive(bitcode::Encode, bitcode::Decode, quops::Encode, quops::Decode, Debug)]
-#[schema(path = "/home/adrian/Programming/JavaScript/QUOP/quop/shared/src/quops/ChatMessage.quops")]
+#[schema(path = "./schemas/ChatMessage.quops")]
+#[allow(dead_code)]
 struct ChatMessage {
     asd: Vec<i32>,
     message: Vec<u8>,
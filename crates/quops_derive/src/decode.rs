@@ -1,9 +1,8 @@
-use std::collections::HashMap;
 use proc_macro2::TokenStream;
 use quote::quote;
-use crate::field::{Field, FieldTrait};
-use crate::schema::Schema;
-use crate::utils::{camel_to_snake_case, has_bytes_field, parse_schema, snake_to_camel_case, TypeHelper};
+use crate::field::{DefaultField, DefaultValue, EnumField, Field, FieldTrait};
+use crate::schema::{Schema, UnionPayload, UnionSchema};
+use crate::utils::{camel_to_snake_case, parse_schema, track_schema_files, unknown_variant, validate_enum_schema, validate_record_schema, validate_union_schema, TypeHelper};
 
 fn decode_nullable(field: &Field, body: TokenStream) -> TokenStream {
     if field.nullable() {
@@ -24,28 +23,84 @@ fn decode_nullable(field: &Field, body: TokenStream) -> TokenStream {
     }
 }
 
-fn generate_decode_field(field: &Field, field_ident: &syn::Ident, field_type: &str) -> TokenStream {
-    let bits = field.bits();
+/// Strips the `Option<...>` of a nullable field's type, leaving the type of the value itself.
+fn value_type<'a>(field: &Field, ty: &'a syn::Type) -> &'a syn::Type {
+    if field.nullable() {
+        TypeHelper::new(ty).inner_type().map_or(ty, |inner| inner.ty())
+    } else {
+        ty
+    }
+}
+
+/// Reads a `u64` prefixed with its bit width minus one, the counterpart of `write_width_prefixed` in `encode.rs`.
+fn read_width_prefixed(field_name: &str, prefix_bits: u8) -> TokenStream {
+    quote! {
+        let bits_width = reader.read(#prefix_bits)? + 1;
+        if bits_width > 64 {
+            let err = format!("Value for field '{}' is {} bits wide, but at most 64 are allowed", #field_name, bits_width);
+            return Err(::quops::DecodeError::OutOfBounds(err));
+        }
+        reader.read(bits_width as u8)?
+    }
+}
+
+/// The value a field with a default takes when its flag bit is cleared.
+fn generate_default_value(default_field: &DefaultField, ty: &syn::Type) -> TokenStream {
+    match &default_field.value {
+        DefaultValue::Int(value) => {
+            let value = proc_macro2::Literal::i128_unsuffixed(*value);
+            quote! { #value }
+        },
+        DefaultValue::Bool(value) => quote! { #value },
+        DefaultValue::Float(value) => {
+            let value = proc_macro2::Literal::f64_unsuffixed(*value);
+            quote! { #value }
+        },
+        DefaultValue::String(value) => quote! { ::std::string::String::from(#value) },
+        DefaultValue::Enum(id) => quote! { <#ty as ::quops::traits::FromDiscriminant>::from_discriminant(#id)? },
+    }
+}
+
+/// Generates an expression reading `field`, where `ty` is the Rust type the field is decoded into.
+fn generate_decode_field(field: &Field, field_name: &str, ty: &syn::Type) -> TokenStream {
+    let bits = field.value_bits();
     debug_assert!(bits <= 255, "Field bits must be in the range of u8 (0-255). Found: {}", bits);
     let bits = bits as u8;
+    let ty = value_type(field, ty);
 
     match field {
         Field::Int(int_field) => {
-            if let (Some(min), Some(max)) = (int_field.min, int_field.max) {
-                decode_nullable(field, quote! {
-                    let value = reader.read(#bits)?;
-                    let value = (value as i32 + #min) as i32;
-                    if !(#min..=#max).contains(&value) {
-                        let err = format!("Value for field '{}' is out of bounds: {}. Expected range: [{}, {}]", stringify!(#field_ident), value, #min, #max);
-                        return Err(::quops::DecodeError::OutOfBounds(err));
-                    }
-                    value
-                })
-            } else {
-                decode_nullable(field, quote! {
-                    let bits_width = reader.read(#bits)? as u8;
-                    reader.read(bits_width)?
-                })
+            match (int_field.min, int_field.max) {
+                (Some(min), Some(max)) => {
+                    decode_nullable(field, quote! {
+                        let value = reader.read(#bits)? as i128 + #min;
+                        if !(#min..=#max).contains(&value) {
+                            let err = format!("Value for field '{}' is out of bounds: {}. Expected range: [{}, {}]", #field_name, value, #min, #max);
+                            return Err(::quops::DecodeError::OutOfBounds(err));
+                        }
+                        ::std::convert::TryInto::try_into(value)?
+                    })
+                },
+                (None, None) => {
+                    let read_value = read_width_prefixed(field_name, bits);
+                    decode_nullable(field, quote! {
+                        let zigzag = { #read_value };
+                        let value = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+                        ::std::convert::TryInto::try_into(value)?
+                    })
+                },
+                (min, max) => {
+                    let value = match (min, max) {
+                        (Some(min), _) => quote! { #min + distance },
+                        (_, Some(max)) => quote! { #max - distance },
+                        _ => unreachable!(),
+                    };
+                    let read_value = read_width_prefixed(field_name, bits);
+                    decode_nullable(field, quote! {
+                        let distance = { #read_value } as i128;
+                        ::std::convert::TryInto::try_into(#value)?
+                    })
+                },
             }
         }
         Field::Boolean(_) => {
@@ -53,73 +108,238 @@ fn generate_decode_field(field: &Field, field_ident: &syn::Ident, field_type: &s
                 reader.read(1)? == 1
             })
         }
-        Field::Bytes(_) => {
+        Field::Float(float_field) => {
+            match float_field.width {
+                32 => decode_nullable(field, quote! {
+                    f32::from_bits(reader.read(32)? as u32)
+                }),
+                _ => decode_nullable(field, quote! {
+                    f64::from_bits(reader.read(64)?)
+                }),
+            }
+        }
+        Field::QuantizedFloat(float_field) => {
+            let (min, max, precision, steps) = (float_field.min, float_field.max, float_field.precision, float_field.steps);
+            let value = if TypeHelper::new(ty).full_type() == "f32" {
+                quote! { value as f32 }
+            } else {
+                quote! { value }
+            };
             decode_nullable(field, quote! {
-                let length = reader.read(#bits)? as usize;
-                let value = bytes.get(buffers_end_index-length..buffers_end_index);
-                buffers_end_index -= length;
-
-                match value {
-                    Some(v) => v.to_vec(),
-                    None => {
-                        let err = format!("Not enough bytes to read field '{}'", stringify!(#field_ident));
-                        return Err(::quops::DecodeError::NotEnoughBytes(err))
-                    },
+                let steps = reader.read(#bits)?;
+                if steps > #steps {
+                    let err = format!("Value for field '{}' is out of bounds: {} steps. Expected at most {}", #field_name, steps, #steps);
+                    return Err(::quops::DecodeError::OutOfBounds(err));
                 }
+                let value = (#min + steps as f64 * #precision).min(#max);
+                #value
             })
         }
-        Field::Enum(_) => {
+        Field::Bytes(bytes_field) => {
+            if let Some(length) = bytes_field.length {
+                let length = length as usize;
+                return decode_nullable(field, quote! {
+                    let mut value = [0u8; #length];
+                    value.copy_from_slice(reader.read_tail_bytes(#length)?);
+                    value
+                });
+            }
             decode_nullable(field, quote! {
-                reader.read(#bits)? as u8
+                let length = reader.read(#bits)? as usize;
+                reader.read_tail_bytes(length)?.to_vec()
             })
         }
-        Field::Record(record_field) => {
-            let name = syn::Ident::new(field_type, proc_macro2::Span::call_site());
-
-            let field_names = record_field.fields.iter().map(|field| {
-                let field_name = syn::Ident::new(&camel_to_snake_case(field.name()), proc_macro2::Span::call_site());
-                let read_call = generate_decode_field(field, &field_name, field_type);
-                quote! { #field_name: { #read_call }.try_into()?, }
-            }).collect::<Vec<_>>();
-
+        Field::String(_) => {
             decode_nullable(field, quote! {
-                #name {
-                    #(#field_names)*
+                let length = reader.read(#bits)? as usize;
+                let bytes = reader.read_tail_bytes(length)?;
+                match ::std::str::from_utf8(bytes) {
+                    Ok(value) => value.to_string(),
+                    Err(err) => {
+                        let err = format!("Value for field '{}' is not valid UTF-8: {}", #field_name, err);
+                        return Err(::quops::DecodeError::InvalidUtf8(err));
+                    }
                 }
             })
         }
+        Field::Enum(_) | Field::Record(_) | Field::Union(_) => {
+            decode_nullable(field, quote! {
+                <#ty as ::quops::traits::DecodeBits>::decode_bits(reader)?
+            })
+        }
         Field::Array(array_field) => {
-            let item_ident = syn::Ident::new("item", proc_macro2::Span::call_site());
-            let decode_item = generate_decode_field(&array_field.items_field, &item_ident, field_type);
+            let item_ty = TypeHelper::new(ty).inner_type().map_or(ty, |inner| inner.ty());
+            let decode_item = generate_decode_field(&array_field.items_field, field_name, item_ty);
+            if let Some(length) = array_field.length {
+                let length = length as usize;
+                // Items are decoded straight into an array, stopping at the first error.
+                return decode_nullable(field, quote! {
+                    let mut decode_item = || -> Result<#item_ty, ::quops::DecodeError> { Ok({ #decode_item }) };
+                    let mut error = None;
+                    let items: [Option<#item_ty>; #length] = ::std::array::from_fn(|_| {
+                        if error.is_some() {
+                            return None;
+                        }
+                        decode_item().map_err(|err| error = Some(err)).ok()
+                    });
+                    if let Some(err) = error {
+                        return Err(err);
+                    }
+                    items.map(|item| item.expect("Every item is decoded when there is no error"))
+                });
+            }
             decode_nullable(field, quote! {
                 let length = reader.read(#bits)? as usize;
                 let mut items = Vec::with_capacity(length);
                 for _ in 0..length {
-                    let #item_ident = {
-                        #decode_item
-                    };
-                    items.push(#item_ident.try_into()?);
+                    items.push({ #decode_item });
                 }
                 items
             })
         }
+        Field::Const(const_field) => {
+            let check = if const_field.checked {
+                let value = const_field.value;
+                quote! {
+                    let value = reader.read(#bits)?;
+                    if value != #value {
+                        let err = format!("Const field '{}' should be {}, got: {}", #field_name, #value, value);
+                        return Err(::quops::DecodeError::OutOfBounds(err));
+                    }
+                }
+            } else {
+                quote! {}
+            };
+            quote! {
+                #check
+                <#ty as ::std::default::Default>::default()
+            }
+        }
+        Field::Default(default_field) => {
+            let default_value = generate_default_value(default_field, ty);
+            let decode_value = generate_decode_field(&default_field.field, field_name, ty);
+            // A cleared bit marks the default.
+            quote! {
+                if reader.read(1)? == 0 {
+                    #default_value
+                } else {
+                    #decode_value
+                }
+            }
+        }
+        Field::Map(map_field) => {
+            let inner_types = TypeHelper::new(ty).inner_types();
+            let (key_ty, value_ty) = match inner_types.as_slice() {
+                [key, value] => (key.ty(), value.ty()),
+                _ => (ty, ty),
+            };
+            let decode_key = generate_decode_field(&map_field.keys_field, field_name, key_ty);
+            let decode_value = generate_decode_field(&map_field.values_field, field_name, value_ty);
+            let max_length = map_field.max_length;
+            decode_nullable(field, quote! {
+                let length = reader.read(#bits)? as usize;
+                if length > #max_length as usize {
+                    let err = format!("Map field '{}' has {} entries, but at most {} are allowed", #field_name, length, #max_length);
+                    return Err(::quops::DecodeError::OutOfBounds(err));
+                }
+                let mut entries: #ty = ::std::default::Default::default();
+                for _ in 0..length {
+                    let key = { #decode_key };
+                    let value = { #decode_value };
+                    if entries.insert(key, value).is_some() {
+                        let err = format!("Map field '{}' contains a duplicate key", #field_name);
+                        return Err(::quops::DecodeError::DuplicateKey(err));
+                    }
+                }
+                entries
+            })
+        }
+    }
+}
+
+/// Reads the tag of the variant, then the payload fields of that variant in schema order.
+fn decode_union(name: &syn::Ident, schema: &UnionSchema, data_enum: &syn::DataEnum) -> TokenStream {
+    if let Err(err) = validate_union_schema(schema, data_enum) {
+        return err.to_compile_error();
+    }
+
+    let tag_bits = schema.tag_bits();
+
+    let match_arms = schema.variants.iter().enumerate().map(|(tag, variant)| {
+        let enum_variant = data_enum.variants.iter()
+            .find(|v| v.ident == variant.name)
+            .expect("Variant presence is checked by validate_union_schema");
+        let variant_ident = &enum_variant.ident;
+        let tag = tag as u64;
+        match &variant.payload {
+            UnionPayload::None => quote! {
+                #tag => #name::#variant_ident,
+            },
+            UnionPayload::Field(field) => {
+                let ty = &enum_variant.fields.iter().next()
+                    .expect("Payload presence is checked by validate_union_schema")
+                    .ty;
+                let read_payload = generate_decode_field(field, &variant.name, ty);
+                quote! {
+                    #tag => #name::#variant_ident({ #read_payload }),
+                }
+            },
+            UnionPayload::Fields(fields) => {
+                let read_fields = fields.iter().map(|field| {
+                    let field_name = camel_to_snake_case(field.name());
+                    let enum_field = enum_variant.fields.iter()
+                        .find(|f| f.ident.as_ref().is_some_and(|ident| *ident == field_name))
+                        .expect("Field presence is checked by validate_union_schema");
+                    let read_call = generate_decode_field(field, field.name(), &enum_field.ty);
+                    let field_ident = &enum_field.ident;
+                    quote! { #field_ident: { #read_call }, }
+                });
+                quote! {
+                    #tag => #name::#variant_ident { #(#read_fields)* },
+                }
+            },
+        }
+    }).collect::<Vec<_>>();
+
+    quote! {
+        impl ::quops::traits::DecodeBits for #name {
+            #[inline(always)]
+            fn decode_bits(reader: &mut ::quops::BitReader) -> Result<Self, ::quops::DecodeError> {
+                let tag = reader.read(#tag_bits)?;
+                Ok(match tag {
+                    #(#match_arms)*
+                    _ => return Err(::quops::DecodeError::OutOfBounds(format!("Invalid {} tag: {}", stringify!(#name), tag))),
+                })
+            }
+        }
+
+        impl ::quops::traits::Decode for #name {}
     }
 }
 
 #[inline]
 pub fn decode(input: syn::DeriveInput) -> TokenStream {
-    let name = &input.ident;
-
-    let schema = match parse_schema(&input) {
-        Ok(schema) => schema,
+    let (schema, schema_files) = match parse_schema(&input) {
+        Ok(parsed) => parsed,
         Err(err) => {
             let err = err.to_string();
             return quote! {
-                compile_error!(concat!("Failed to parse schema: ", #err));
+                compile_error!(#err);
             }.into();
         }
     };
 
+    let track_files = track_schema_files(&schema_files);
+    let expanded = decode_schema(&input, schema);
+    quote! {
+        #track_files
+        #expanded
+    }
+}
+
+fn decode_schema(input: &syn::DeriveInput, schema: Schema) -> TokenStream {
+    let name = &input.ident;
+
     match &input.data {
         syn::Data::Struct(data_struct) => {
             let schema = match schema {
@@ -131,94 +351,127 @@ pub fn decode(input: syn::DeriveInput) -> TokenStream {
                 }
             };
 
-            let mut types = HashMap::new();
-            for field in &data_struct.fields {
-                let field_name = field.ident.as_ref().unwrap().to_string();
-                let field_name_json = snake_to_camel_case(&field_name);
-                let type_helper = TypeHelper::new(&field.ty);
-                let ty = {
-                    if let Some(ty) = type_helper.inner_type() {
-                        ty.full_type()
-                    } else {
-                        type_helper.full_type()
-                    }
-                };
-                types.insert(field_name_json, ty);
+            if let Err(err) = validate_record_schema(&schema, data_struct) {
+                return err.to_compile_error();
             }
 
-            let struct_field_names = schema.fields.iter().map(|field| {
-                let field_name = field.name();
-                let ty = types.get(field_name).expect(&format!("Field '{}' not found in types map", field_name));
-                let field_name = syn::Ident::new(&camel_to_snake_case(field.name()), proc_macro2::Span::call_site());
-                let read_call = generate_decode_field(field, &field_name, &ty);
-                quote! { #field_name: { #read_call }.try_into()?, }
+            // Fields are read into locals first, since const fields left out of the struct are still checked in wire order.
+            let mut struct_field_names = Vec::new();
+            let mut top_level_read_calls = Vec::new();
+            let read_calls = schema.fields.iter().map(|field| {
+                let field_name = camel_to_snake_case(field.name());
+                let struct_field = data_struct.fields.iter()
+                    .find(|f| f.ident.as_ref().is_some_and(|ident| *ident == field_name));
+                let Some(struct_field) = struct_field else {
+                    let read_call = generate_decode_field(field, field.name(), &syn::parse_quote!(()));
+                    top_level_read_calls.push(quote! { { #read_call }; });
+                    return quote! { { #read_call }; };
+                };
+                let read_call = generate_decode_field(field, field.name(), &struct_field.ty);
+                let field_name = syn::Ident::new(&field_name, proc_macro2::Span::call_site());
+                let local_name = quote::format_ident!("field_{}", field_name);
+                struct_field_names.push(quote! { #field_name: #local_name, });
+                // Only the message itself ends where its last field does, so only there can a field appended
+                // after a payload was written be told apart: it reads as the zero padding or the end of the input.
+                let top_level_read_call = match field {
+                    Field::Default(default_field) => {
+                        let default_value = generate_default_value(default_field, &struct_field.ty);
+                        quote! {
+                            if reader.remaining_bits() == 0 {
+                                #default_value
+                            } else {
+                                #read_call
+                            }
+                        }
+                    },
+                    _ => read_call.clone(),
+                };
+                top_level_read_calls.push(quote! { let #local_name = { #top_level_read_call }; });
+                quote! { let #local_name = { #read_call }; }
             }).collect::<Vec<_>>();
 
-            let schema_has_bytes_field = has_bytes_field(&schema.fields);
-            let create_buffers_end_index = if schema_has_bytes_field {
-                quote! { let mut buffers_end_index = bytes.len(); }
-            } else {
-                quote! {}
-            };
+            let decode_fn = schema.fields.iter().any(|field| matches!(field, Field::Default(_))).then(|| quote! {
+                #[inline(always)]
+                fn decode(bytes: &[u8]) -> Result<Self, ::quops::DecodeError> {
+                    let reader = &mut ::quops::BitReader::new(bytes);
+                    #(#top_level_read_calls)*
+                    Ok(#name {
+                        #(#struct_field_names)*
+                    })
+                }
+            });
 
             quote! {
-                impl ::quops::traits::Decode for #name {
+                impl ::quops::traits::DecodeBits for #name {
                     #[inline(always)]
-                    fn decode(bytes: &[u8]) -> Result<Self, ::quops::DecodeError> {
-                        let mut reader = ::quops::BitReader::new(bytes);
-                        #create_buffers_end_index
-
+                    fn decode_bits(reader: &mut ::quops::BitReader) -> Result<Self, ::quops::DecodeError> {
+                        #(#read_calls)*
                         Ok(#name {
                             #(#struct_field_names)*
                         })
                     }
                 }
+
+                impl ::quops::traits::Decode for #name {
+                    #decode_fn
+                }
             }
         }
         syn::Data::Enum(data_enum) => {
             let schema = match schema {
                 Schema::Enum(enum_schema) => enum_schema,
+                Schema::Union(union_schema) => return decode_union(name, &union_schema, data_enum).into(),
                 _ => {
                     return quote! {
-                        compile_error!("Decode can only be derived for enums with 'enum' schema type");
+                        compile_error!("Decode can only be derived for enums with 'enum' or 'union' schema type");
                     }.into();
                 }
             };
 
-            for variant in &data_enum.variants {
-                let variant_name = variant.ident.to_string();
-                if !schema.variants.iter().any(|v| *v == variant_name) {
-                    return quote! {
-                        compile_error!(concat!("Variant '", #variant_name, "' is not present in schema"));
-                    }.into();
-                }
-            }
-            for variant in &schema.variants {
-                if !data_enum.variants.iter().any(|v| v.ident.to_string() == *variant) {
-                    return quote! {
-                        compile_error!(concat!("Variant '", #variant, "' is not present in enum"));
-                    }.into();
-                }
+            if let Err(err) = validate_enum_schema(&schema, data_enum) {
+                return err.to_compile_error();
             }
 
-            let match_arms = data_enum.variants.iter().enumerate().map(|(index, variant)| {
-                let index = index as u8;
+            let bits = Field::Enum(EnumField::new(&name.to_string(), schema.id_bits(), false)).bits() as u8;
+
+            let match_arms = schema.variants.iter().map(|variant| {
+                let variant_name = syn::Ident::new(&variant.name, proc_macro2::Span::call_site());
+                let id = variant.id;
                 quote! {
-                    #index => Ok(#name::#variant),
+                    #id => Ok(#name::#variant_name),
                 }
             }).collect::<Vec<_>>();
 
-            quote! {
-                impl TryInto<#name> for u8 {
-                    type Error = ::quops::DecodeError;
+            let fallback_arm = match unknown_variant(&schema, data_enum) {
+                Ok(Some(unknown)) => {
+                    let variant_name = &unknown.ident;
+                    quote! { _ => Ok(#name::#variant_name(discriminant)), }
+                },
+                _ => quote! {
+                    _ => Err(::quops::DecodeError::OutOfBounds(format!("Invalid {} value: {}", stringify!(#name), discriminant))),
+                },
+            };
 
-                    fn try_into(self) -> Result<#name, Self::Error> {
-                        match self {
+            quote! {
+                impl ::quops::traits::FromDiscriminant for #name {
+                    #[inline(always)]
+                    fn from_discriminant(discriminant: u64) -> Result<Self, ::quops::DecodeError> {
+                        match discriminant {
                             #(#match_arms)*
-                            _ => Err(::quops::DecodeError::OutOfBounds(format!("Invalid {} value: {}", stringify!(#name), self))),
+                            #fallback_arm
                         }
                     }
                 }
+
+                impl ::quops::traits::DecodeBits for #name {
+                    #[inline(always)]
+                    fn decode_bits(reader: &mut ::quops::BitReader) -> Result<Self, ::quops::DecodeError> {
+                        let discriminant = reader.read(#bits)?;
+                        ::quops::traits::FromDiscriminant::from_discriminant(discriminant)
+                    }
+                }
+
+                impl ::quops::traits::Decode for #name {}
             }
         }
         _ => quote! {}
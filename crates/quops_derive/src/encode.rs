@@ -1,17 +1,17 @@
 use proc_macro2::TokenStream;
 use quote::quote;
-use crate::field::{Field, FieldTrait};
-use crate::schema::Schema;
-use crate::utils::{camel_to_snake_case, has_bytes_field, parse_schema, validate_enum_schema, validate_record_schema};
+use crate::field::{DefaultValue, EnumField, Field, FieldTrait};
+use crate::schema::{Schema, UnionPayload, UnionSchema};
+use crate::utils::{camel_to_snake_case, parse_schema, track_schema_files, unknown_variant, validate_enum_schema, validate_record_schema, validate_union_schema};
 
 fn encode_nullable<F>(field: &Field, var: &TokenStream, get_body: F) -> TokenStream
 where
     F: Fn(TokenStream) -> TokenStream
 {
     if field.nullable() {
-        let body = get_body(quote! { value });
+        let body = get_body(quote! { (*value) });
         quote! {
-            if let Some(value) = #var {
+            if let Some(value) = &#var {
                 writer.write(1, 1)?;
                 #body
             } else {
@@ -23,28 +23,72 @@ where
     }
 }
 
+/// Writes the `u64` in `value` prefixed with its bit width minus one, so that zero still takes a single bit.
+fn write_width_prefixed(value: &TokenStream, prefix_bits: u8) -> TokenStream {
+    quote! {
+        let bits_width = (64 - #value.leading_zeros()).max(1) as u8;
+        writer.write((bits_width - 1) as u64, #prefix_bits)?;
+        writer.write(#value, bits_width)?;
+    }
+}
+
+/// Generates the code writing `field`, where `field_ident` is a place expression of the field's own type
+/// (e.g. `self.foo` or `(*item)`), never a reference to it.
 fn generate_encode_field(field: &Field, field_ident: &TokenStream) -> TokenStream {
-    let bits = field.bits();
+    let bits = field.value_bits();
     debug_assert!(bits <= 255, "Field bits must be in the range of u8 (0-255). Found: {}", bits);
     let bits = bits as u8;
     let field_name = field.name();
 
     match field {
         Field::Int(int_field) => {
-            if let (Some(min), Some(max)) = (int_field.min, int_field.max) {
-                encode_nullable(field, field_ident, |var| quote! {
-                    if !(#min..=#max).contains(&(#var as i32)) {
-                        let err = format!("Value for field '{}' is out of bounds: {}. Expected range: [{}, {}]", stringify!(#var), #var, #min, #max);
-                        return Err(::quops::EncodeError::OutOfBounds(err));
-                    }
-                    writer.write((#var as i32 - #min) as u64, #bits)?;
-                })
-            } else {
-                encode_nullable(field, field_ident, |var| quote! {
-                    let bits_width = (64 - (#var as u64).leading_zeros()) as u8;
-                    writer.write(bits_width as u64, #bits)?;
-                    writer.write(#var as u64, bits_width)?;
-                })
+            match (int_field.min, int_field.max) {
+                (Some(min), Some(max)) => {
+                    encode_nullable(field, field_ident, |var| quote! {
+                        let value = #var as i128;
+                        if !(#min..=#max).contains(&value) {
+                            let err = format!("Value for field '{}' is out of bounds: {}. Expected range: [{}, {}]", #field_name, value, #min, #max);
+                            return Err(::quops::EncodeError::OutOfBounds(err));
+                        }
+                        writer.write((value - #min) as u64, #bits)?;
+                    })
+                },
+                (None, None) => {
+                    // Unbounded ints are zigzag-mapped, so small negative values stay small.
+                    encode_nullable(field, field_ident, |var| {
+                        let write_value = write_width_prefixed(&quote! { zigzag }, bits);
+                        quote! {
+                            let value: i64 = ::std::convert::TryInto::try_into(#var).map_err(|_| {
+                                let err = format!("Value for field '{}' does not fit in 64 bits", #field_name);
+                                ::quops::EncodeError::OutOfBounds(err)
+                            })?;
+                            let zigzag = ((value << 1) ^ (value >> 63)) as u64;
+                            #write_value
+                        }
+                    })
+                },
+                (min, max) => {
+                    // Half-bounded ints are written as their distance from the bound.
+                    let (distance, range) = match (min, max) {
+                        (Some(min), _) => (quote! { value - #min }, format!("{}..", min)),
+                        (_, Some(max)) => (quote! { #max - value }, format!("..={}", max)),
+                        _ => unreachable!(),
+                    };
+                    encode_nullable(field, field_ident, |var| {
+                        let write_value = write_width_prefixed(&quote! { distance }, bits);
+                        quote! {
+                            let value = #var as i128;
+                            let distance: u64 = match ::std::convert::TryInto::try_into(#distance) {
+                                Ok(distance) => distance,
+                                Err(_) => {
+                                    let err = format!("Value for field '{}' is out of bounds: {}. Expected range: {}", #field_name, value, #range);
+                                    return Err(::quops::EncodeError::OutOfBounds(err));
+                                },
+                            };
+                            #write_value
+                        }
+                    })
+                },
             }
         },
         Field::Boolean(_) => {
@@ -52,19 +96,60 @@ fn generate_encode_field(field: &Field, field_ident: &TokenStream) -> TokenStrea
                 writer.write(#var as u64, 1)?;
             })
         },
-        Field::Enum(_) => {
+        Field::Float(float_field) => {
+            let (width, to_bits) = match float_field.width {
+                32 => (32u8, quote! { to_bits() as u64 }),
+                _ => (64u8, quote! { to_bits() }),
+            };
+            encode_nullable(field, field_ident, |var| quote! {
+                writer.write(#var.#to_bits, #width)?;
+            })
+        },
+        Field::QuantizedFloat(float_field) => {
+            let (min, max, precision, steps) = (float_field.min, float_field.max, float_field.precision, float_field.steps);
+            let out_of_bounds = quote! {
+                let err = format!("Value for field '{}' is out of bounds: {}. Expected range: [{}, {}]", #field_name, value, #min, #max);
+                return Err(::quops::EncodeError::OutOfBounds(err));
+            };
+            let check_bounds = if float_field.clamp {
+                quote! {
+                    if value.is_nan() {
+                        #out_of_bounds
+                    }
+                    let value = value.clamp(#min, #max);
+                }
+            } else {
+                quote! {
+                    if !(#min..=#max).contains(&value) {
+                        #out_of_bounds
+                    }
+                }
+            };
             encode_nullable(field, field_ident, |var| quote! {
-                writer.write(::quops::traits::AsU64::as_u64(&#var)?, #bits)?;
+                let value = f64::from(#var);
+                #check_bounds
+                let steps = (((value - #min) / #precision).round() as u64).min(#steps);
+                writer.write(steps, #bits)?;
             })
         },
-        Field::Bytes(bytes_field) => {
-            let max_length = bytes_field.max_length.unwrap_or(2u32.saturating_pow(2u32.saturating_pow(bits as u32)));
+        Field::Bytes(_) | Field::String(_) => {
+            let (kind, max_length, fixed) = match field {
+                Field::String(string_field) => ("String", string_field.max_length, false),
+                Field::Bytes(bytes_field) => ("Bytes", bytes_field.max_length, bytes_field.length.is_some()),
+                _ => unreachable!(),
+            };
+            if fixed {
+                return encode_nullable(field, field_ident, |var| quote! {
+                    writer.defer_bytes(&#var);
+                });
+            }
+            let max_length = max_length.unwrap_or(2u32.saturating_pow(2u32.saturating_pow(bits as u32)));
 
             encode_nullable(field, field_ident, |var| {
                 let check_bounds = if max_length < u32::MAX {
                     quote! {
                         if #var.len() > #max_length as usize {
-                            let err = format!("Bytes length exceeds maximum for field: {:?}, got: {}", #field_name, #var.len());
+                            let err = format!("{} length exceeds maximum for field: {:?}, got: {}", #kind, #field_name, #var.len());
                             return Err(::quops::EncodeError::OutOfBounds(err));
                         }
                     }
@@ -74,63 +159,190 @@ fn generate_encode_field(field: &Field, field_ident: &TokenStream) -> TokenStrea
 
                 quote! {
                     #check_bounds
-                    buffers.push(&#var);
+                    writer.defer_bytes(::std::convert::AsRef::<[u8]>::as_ref(&#var));
                     writer.write(#var.len() as u64, #bits)?;
                 }
             })
         },
-        Field::Record(record_field) => {
-            // If the field is nullable, we need to match it by reference to avoid moving the inner value.
-            // Could this be done in a more efficient/readable way?
-            let field_ident = if field.nullable() {
-                quote! { &#field_ident }
-            } else {
-                quote! { #field_ident }
-            };
-            encode_nullable(field, &field_ident, |var| {
-                let mut res = quote! {};
-                for sub_field in &record_field.fields {
-                    let sub_field_ident = syn::Ident::new(&camel_to_snake_case(sub_field.name()), proc_macro2::Span::call_site());
-                    let sub_field_ident = quote! { #var.#sub_field_ident };
-                    res.extend(generate_encode_field(sub_field, &sub_field_ident));
-                }
-                res
+        Field::Enum(_) | Field::Record(_) | Field::Union(_) => {
+            encode_nullable(field, field_ident, |var| quote! {
+                ::quops::traits::EncodeBits::encode_bits(&#var, writer)?;
             })
         },
         Field::Array(array_field) => {
-            let item_ident = quote! { item };
-            let encode_item = generate_encode_field(&array_field.items_field, &item_ident);
+            let encode_item = generate_encode_field(&array_field.items_field, &quote! { (*item) });
+            let fixed = array_field.length.is_some();
             encode_nullable(field, field_ident, |var| {
-                let item_ident = if array_field.items_field.is_primitive() {
-                    quote! { &#item_ident }
+                let write_length = if fixed {
+                    quote! {}
                 } else {
-                    item_ident.clone()
+                    quote! { writer.write(#var.len() as u64, #bits)?; }
                 };
                 quote! {
-                    writer.write(#var.len() as u64, #bits)?;
-                    for #item_ident in #var.iter() {
+                    #write_length
+                    for item in #var.iter() {
                         #encode_item
                     }
                 }
             })
         }
+        Field::Const(const_field) => {
+            if !const_field.checked {
+                return quote! {};
+            }
+            let value = const_field.value;
+            quote! {
+                writer.write(#value, #bits)?;
+            }
+        }
+        Field::Default(default_field) => {
+            let is_default = match &default_field.value {
+                DefaultValue::Int(value) => {
+                    let value = proc_macro2::Literal::i128_unsuffixed(*value);
+                    quote! { #field_ident == #value }
+                },
+                DefaultValue::Bool(value) => quote! { #field_ident == #value },
+                DefaultValue::Float(value) => {
+                    let value = proc_macro2::Literal::f64_unsuffixed(*value);
+                    quote! { #field_ident == #value }
+                },
+                DefaultValue::String(value) => quote! { #field_ident == #value },
+                DefaultValue::Enum(id) => quote! { ::quops::traits::ToDiscriminant::to_discriminant(&#field_ident) == #id },
+            };
+            let encode_value = generate_encode_field(&default_field.field, field_ident);
+            quote! {
+                if #is_default {
+                    writer.write(0, 1)?;
+                } else {
+                    writer.write(1, 1)?;
+                    #encode_value
+                }
+            }
+        }
+        Field::Map(map_field) => {
+            let encode_key = generate_encode_field(&map_field.keys_field, &quote! { (*key) });
+            let encode_value = generate_encode_field(&map_field.values_field, &quote! { (*value) });
+            let max_length = map_field.max_length;
+            encode_nullable(field, field_ident, |var| quote! {
+                if #var.len() > #max_length as usize {
+                    let err = format!("Map length exceeds maximum for field: {:?}, got: {}", #field_name, #var.len());
+                    return Err(::quops::EncodeError::OutOfBounds(err));
+                }
+                writer.write(#var.len() as u64, #bits)?;
+                for (key, value) in #var.iter() {
+                    { #encode_key }
+                    { #encode_value }
+                }
+            })
+        }
+    }
+}
+
+/// Writes the tag of the variant, followed by its payload fields in schema order.
+fn encode_union(name: &syn::Ident, schema: &UnionSchema, data_enum: &syn::DataEnum) -> TokenStream {
+    if let Err(err) = validate_union_schema(schema, data_enum) {
+        return err.to_compile_error();
+    }
+
+    let tag_bits = schema.tag_bits();
+
+    let (write_arms, bits_arms): (Vec<_>, Vec<_>) = schema.variants.iter().enumerate().map(|(tag, variant)| {
+        let variant_ident = syn::Ident::new(&variant.name, proc_macro2::Span::call_site());
+        let tag = tag as u64;
+        match &variant.payload {
+            UnionPayload::None => {
+                let bits = tag_bits as u32;
+                (quote! {
+                    #name::#variant_ident => {
+                        writer.write(#tag, #tag_bits)?;
+                    }
+                }, quote! {
+                    #name::#variant_ident => #bits,
+                })
+            },
+            UnionPayload::Field(field) => {
+                let bits = tag_bits as u32 + field.bits();
+                let write_payload = generate_encode_field(field, &quote! { (*value) });
+                (quote! {
+                    #name::#variant_ident(value) => {
+                        writer.write(#tag, #tag_bits)?;
+                        #write_payload
+                    }
+                }, quote! {
+                    #name::#variant_ident(..) => #bits,
+                })
+            },
+            UnionPayload::Fields(fields) => {
+                let bits = tag_bits as u32 + fields.iter().map(|f| f.bits()).sum::<u32>();
+                let field_idents = fields.iter()
+                    .map(|f| syn::Ident::new(&camel_to_snake_case(f.name()), proc_macro2::Span::call_site()))
+                    .collect::<Vec<_>>();
+                // Fields are bound to prefixed locals, so that a field named like a generated local such as `writer` does not shadow it.
+                let local_names = field_idents.iter()
+                    .map(|field_ident| quote::format_ident!("field_{}", field_ident))
+                    .collect::<Vec<_>>();
+                let write_payload = fields.iter().zip(&local_names).map(|(field, local_name)| {
+                    let write_field = generate_encode_field(field, &quote! { (*#local_name) });
+                    quote! { { #write_field } }
+                });
+                (quote! {
+                    #name::#variant_ident { #(#field_idents: #local_names),* } => {
+                        writer.write(#tag, #tag_bits)?;
+                        #(#write_payload)*
+                    }
+                }, quote! {
+                    #name::#variant_ident { .. } => #bits,
+                })
+            },
+        }
+    }).unzip();
+
+    quote! {
+        impl ::quops::traits::EncodeBits for #name {
+            #[inline(always)]
+            fn encode_bits<O: ::quops::BitOutput>(&self, writer: &mut ::quops::BitWriter<O>) -> Result<(), ::quops::EncodeError> {
+                match self {
+                    #(#write_arms)*
+                }
+                Ok(())
+            }
+        }
+
+        impl ::quops::traits::Encode for #name {
+            #[inline(always)]
+            fn size_hint(&self) -> usize {
+                let bits: u32 = match self {
+                    #(#bits_arms)*
+                };
+                bits.div_ceil(8) as usize
+            }
+        }
     }
 }
 
 #[inline]
 pub fn encode(input: syn::DeriveInput) -> TokenStream {
-    let name = &input.ident;
-    
-    let schema = match parse_schema(&input) {
-        Ok(schema) => schema,
+    let (schema, schema_files) = match parse_schema(&input) {
+        Ok(parsed) => parsed,
         Err(err) => {
             let err = err.to_string();
             return quote! {
-                compile_error!(concat!("Failed to parse schema: ", #err));
+                compile_error!(#err);
             }.into();
         }
     };
 
+    let track_files = track_schema_files(&schema_files);
+    let expanded = encode_schema(&input, schema);
+    quote! {
+        #track_files
+        #expanded
+    }
+}
+
+fn encode_schema(input: &syn::DeriveInput, schema: Schema) -> TokenStream {
+    let name = &input.ident;
+
     match &input.data {
         syn::Data::Struct(data_struct) => {
             let schema = match schema {
@@ -143,9 +355,7 @@ pub fn encode(input: syn::DeriveInput) -> TokenStream {
             };
 
             if let Err(err) = validate_record_schema(&schema, data_struct) {
-                return quote! {
-                    compile_error!(concat!("Schema validation error: ", #err));
-                }.into();
+                return err.to_compile_error();
             }
 
             let field_write_calls = schema.fields.iter().map(|field| {
@@ -154,56 +364,50 @@ pub fn encode(input: syn::DeriveInput) -> TokenStream {
                 generate_encode_field(field, &field_ident)
             }).collect::<Vec<_>>();
 
-            let schema_has_bytes_field = has_bytes_field(&schema.fields);
-            let (create_buffers, return_bin) = if schema_has_bytes_field {
-                (
-                    quote! { let mut buffers = Vec::new(); },
-                    quote! {
-                        Ok({
-                            let mut bin = writer.into_bytes();
-                            for buf in buffers.iter().rev() {
-                                bin.extend_from_slice(buf);
-                            }
-                            bin
-                        })
-                    }
-                )
-            } else {
-                (
-                    quote! {},
-                    quote! {
-                        Ok(writer.into_bytes())
-                    }
-                )
-            };
-
             let schema_bits = schema.bits();
 
+            let len_of = |f: &Field| {
+                let name = syn::Ident::new(&camel_to_snake_case(f.name()), proc_macro2::Span::call_site());
+                if f.nullable() {
+                    quote! { self.#name.as_ref().map_or(0, |v| v.len()) as u32 }
+                } else {
+                    quote! { self.#name.len() as u32 }
+                }
+            };
             let field_bits = schema.fields.iter().filter_map(|f| {
-                let name = syn::Ident::new(f.name(), proc_macro2::Span::call_site());
                 match f {
                     Field::Array(array_field) => {
                         let items_bits = array_field.items_field.as_ref().bits();
-                        Some(quote! { #items_bits * self.#name.len() as u32 })
+                        let len = len_of(f);
+                        Some(quote! { #items_bits * #len })
+                    },
+                    Field::Map(map_field) => {
+                        let entry_bits = map_field.keys_field.bits() + map_field.values_field.bits();
+                        let len = len_of(f);
+                        Some(quote! { #entry_bits * #len })
                     },
                     _ => None
                 }
             }).collect::<Vec<_>>();
             let bytes_fields_bytes = schema.fields.iter().filter_map(|f| {
-                if !matches!(f, Field::Bytes(_)) { return None; }
-                let name = syn::Ident::new(f.name(), proc_macro2::Span::call_site());
-                Some(quote! { self.#name.len() as u32 })
+                if !matches!(f, Field::Bytes(_) | Field::String(_)) { return None; }
+                Some(len_of(f))
             }).collect::<Vec<_>>();
 
             quote! {
-                impl ::quops::traits::Encode for #name {
+                impl ::quops::traits::EncodeBits for #name {
                     #[inline(always)]
-                    fn encode(&self) -> Result<Vec<u8>, ::quops::EncodeError> {
-                        let total_bytes = ((#schema_bits #(+ #field_bits)*) + 7) / 8 #(+ #bytes_fields_bytes)*;
-                        let mut writer = ::quops::BitWriter::with_capacity(total_bytes as usize);
-                        #create_buffers
+                    fn encode_bits<O: ::quops::BitOutput>(&self, writer: &mut ::quops::BitWriter<O>) -> Result<(), ::quops::EncodeError> {
                         #(#field_write_calls)*
-                        #return_bin
+                        Ok(())
+                    }
+                }
+
+                impl ::quops::traits::Encode for #name {
+                    #[inline(always)]
+                    #[allow(clippy::manual_div_ceil)]
+                    fn size_hint(&self) -> usize {
+                        (((#schema_bits #(+ #field_bits)*) + 7) / 8 #(+ #bytes_fields_bytes)*) as usize
                     }
                 }
             }.into()
@@ -211,36 +415,59 @@ pub fn encode(input: syn::DeriveInput) -> TokenStream {
         syn::Data::Enum(data_enum) => {
             let schema = match schema {
                 Schema::Enum(enum_schema) => enum_schema,
+                Schema::Union(union_schema) => return encode_union(name, &union_schema, data_enum).into(),
                 _ => {
                     return quote! {
-                        compile_error!("Encode can only be derived for enums with 'enum' schema type");
+                        compile_error!("Encode can only be derived for enums with 'enum' or 'union' schema type");
                     }.into();
                 }
             };
 
             if let Err(err) = validate_enum_schema(&schema, data_enum) {
-                return quote! {
-                    compile_error!(concat!("Schema validation error: ", #err));
-                }.into();
+                return err.to_compile_error();
             }
 
-            let match_arms = data_enum.variants.iter().enumerate().map(|(index, variant)| {
-                let variant_name = &variant.ident;
-                let index = index as u64;
+            let mut match_arms = schema.variants.iter().map(|variant| {
+                let variant_name = syn::Ident::new(&variant.name, proc_macro2::Span::call_site());
+                let id = variant.id;
                 quote! {
-                    #name::#variant_name => Ok(#index),
+                    #name::#variant_name => #id,
                 }
             }).collect::<Vec<_>>();
+            // Validated above, so the variant is well-formed if present. Its raw id is written as is, even when it belongs to a known variant.
+            if let Ok(Some(unknown)) = unknown_variant(&schema, data_enum) {
+                let variant_name = &unknown.ident;
+                match_arms.push(quote! {
+                    #name::#variant_name(value) => *value,
+                });
+            }
+
+            let bits = Field::Enum(EnumField::new(&name.to_string(), schema.id_bits(), false)).bits() as u8;
 
             quote! {
-                impl ::quops::traits::AsU64 for #name {
+                impl ::quops::traits::ToDiscriminant for #name {
                     #[inline(always)]
-                    fn as_u64(&self) -> Result<u64, ::quops::EncodeError> {
+                    fn to_discriminant(&self) -> u64 {
                         match self {
                             #(#match_arms)*
                         }
                     }
                 }
+
+                impl ::quops::traits::EncodeBits for #name {
+                    #[inline(always)]
+                    fn encode_bits<O: ::quops::BitOutput>(&self, writer: &mut ::quops::BitWriter<O>) -> Result<(), ::quops::EncodeError> {
+                        writer.write(::quops::traits::ToDiscriminant::to_discriminant(self), #bits)?;
+                        Ok(())
+                    }
+                }
+
+                impl ::quops::traits::Encode for #name {
+                    #[inline(always)]
+                    fn size_hint(&self) -> usize {
+                        (#bits as usize).div_ceil(8)
+                    }
+                }
             }.into()
         },
         _ => {
@@ -0,0 +1,263 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+/// An error in a schema file, pointing at the JSON node that caused it.
+#[derive(Debug)]
+pub struct SchemaError {
+    /// The schema file containing the node, once known.
+    pub file: Option<PathBuf>,
+    /// JSON pointer to the node, such as `/fields/score/min`.
+    pub pointer: String,
+    /// The 1-based line and column of the node in the file.
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl SchemaError {
+    pub fn new(message: impl Into<String>) -> Self {
+        SchemaError { file: None, pointer: String::new(), position: None, message: message.into() }
+    }
+
+    /// Moves the error one level down, into the member or element `segment` of the node it points at.
+    pub fn in_node(mut self, segment: impl Display) -> Self {
+        if self.file.is_none() {
+            let segment = segment.to_string().replace('~', "~0").replace('/', "~1");
+            self.pointer = format!("/{}{}", segment, self.pointer);
+        }
+        self
+    }
+
+    /// Places the error in the schema file with the given contents. Errors from dependencies keep their own file.
+    pub fn in_file(mut self, file: &Path, source: &str) -> Self {
+        if self.file.is_none() {
+            self.position = locate(source, &self.pointer);
+            self.file = Some(file.to_path_buf());
+        }
+        self
+    }
+
+    pub fn from_json(file: &Path, err: serde_json::Error) -> Self {
+        SchemaError {
+            file: Some(file.to_path_buf()),
+            pointer: String::new(),
+            position: Some((err.line(), err.column())),
+            message: format!("Invalid JSON: {}", err.to_string().trim_end_matches(&format!(" at line {} column {}", err.line(), err.column()))),
+        }
+    }
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}", file.display())?;
+            match self.position {
+                Some((line, column)) => write!(f, ":{}:{}", line, column)?,
+                None if !self.pointer.is_empty() => write!(f, " at {}", self.pointer)?,
+                None => {},
+            }
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl From<String> for SchemaError {
+    fn from(message: String) -> Self {
+        SchemaError::new(message)
+    }
+}
+
+impl From<&str> for SchemaError {
+    fn from(message: &str) -> Self {
+        SchemaError::new(message)
+    }
+}
+
+/// Finds the line and column of the node at `pointer` in the JSON `source`, which must be valid JSON.
+fn locate(source: &str, pointer: &str) -> Option<(usize, usize)> {
+    let bytes = source.as_bytes();
+    let mut pos = skip_whitespace(bytes, 0);
+    for segment in pointer.split('/').skip(1) {
+        let segment = segment.replace("~1", "/").replace("~0", "~");
+        pos = match bytes.get(pos)? {
+            b'{' => find_member(source, pos, &segment)?,
+            b'[' => find_element(bytes, pos, segment.parse().ok()?)?,
+            _ => return None,
+        };
+    }
+
+    let before = &source[..pos];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+    Some((line, column))
+}
+
+fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+    while bytes.get(pos).is_some_and(u8::is_ascii_whitespace) {
+        pos += 1;
+    }
+    pos
+}
+
+/// Returns the position after the string starting at `pos`.
+fn skip_string(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    pos += 1;
+    loop {
+        match bytes.get(pos)? {
+            b'\\' => pos += 2,
+            b'"' => return Some(pos + 1),
+            _ => pos += 1,
+        }
+    }
+}
+
+/// Returns the position after the value starting at `pos`.
+fn skip_value(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    match bytes.get(pos)? {
+        b'"' => skip_string(bytes, pos),
+        b'{' | b'[' => {
+            let mut depth = 0usize;
+            loop {
+                match bytes.get(pos)? {
+                    b'"' => {
+                        pos = skip_string(bytes, pos)?;
+                        continue;
+                    },
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(pos + 1);
+                        }
+                    },
+                    _ => {},
+                }
+                pos += 1;
+            }
+        },
+        _ => {
+            while bytes.get(pos).is_some_and(|b| !b.is_ascii_whitespace() && !matches!(b, b',' | b'}' | b']')) {
+                pos += 1;
+            }
+            Some(pos)
+        },
+    }
+}
+
+/// Returns the position of the value of member `key` in the object starting at `pos`.
+fn find_member(source: &str, pos: usize, key: &str) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut pos = skip_whitespace(bytes, pos + 1);
+    while bytes.get(pos) == Some(&b'"') {
+        let key_end = skip_string(bytes, pos)?;
+        let member_key = serde_json::from_str::<String>(&source[pos..key_end]).ok()?;
+        pos = skip_whitespace(bytes, key_end);
+        if bytes.get(pos) != Some(&b':') {
+            return None;
+        }
+        pos = skip_whitespace(bytes, pos + 1);
+        if member_key == key {
+            return Some(pos);
+        }
+        pos = skip_whitespace(bytes, skip_value(bytes, pos)?);
+        if bytes.get(pos) != Some(&b',') {
+            return None;
+        }
+        pos = skip_whitespace(bytes, pos + 1);
+    }
+    None
+}
+
+/// Returns the position of element `index` in the array starting at `pos`.
+fn find_element(bytes: &[u8], pos: usize, index: usize) -> Option<usize> {
+    let mut pos = skip_whitespace(bytes, pos + 1);
+    for _ in 0..index {
+        if bytes.get(pos) == Some(&b']') {
+            return None;
+        }
+        pos = skip_whitespace(bytes, skip_value(bytes, pos)?);
+        if bytes.get(pos) != Some(&b',') {
+            return None;
+        }
+        pos = skip_whitespace(bytes, pos + 1);
+    }
+    (bytes.get(pos) != Some(&b']')).then_some(pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Schema;
+
+    const SOURCE: &str = r#"{
+  "name": "Player",
+  "note": "{ not [ an object",
+  "fields": {
+    "id": { "type": "int", "min": 0 },
+    "a/b~c": "string",
+    "tags": ["x", { "y": [1, 2] }]
+  }
+}"#;
+
+    #[test]
+    fn locates_nested_members() {
+        assert_eq!(locate(SOURCE, ""), Some((1, 1)));
+        assert_eq!(locate(SOURCE, "/fields"), Some((4, 13)));
+        assert_eq!(locate(SOURCE, "/fields/id/min"), Some((5, 35)));
+    }
+
+    #[test]
+    fn locates_array_elements() {
+        assert_eq!(locate(SOURCE, "/fields/tags/0"), Some((7, 14)));
+        assert_eq!(locate(SOURCE, "/fields/tags/1/y/1"), Some((7, 30)));
+        assert_eq!(locate(SOURCE, "/fields/tags/2"), None);
+    }
+
+    #[test]
+    fn locates_escaped_keys() {
+        assert_eq!(locate(SOURCE, "/fields/a~1b~0c"), Some((6, 14)));
+        let err = SchemaError::new("Invalid").in_node("a/b~c").in_node("fields");
+        assert_eq!(err.pointer, "/fields/a~1b~0c");
+    }
+
+    #[test]
+    fn skips_strings_containing_brackets() {
+        assert_eq!(locate(SOURCE, "/name"), Some((2, 11)));
+        assert_eq!(locate(SOURCE, "/fields/id"), Some((5, 11)));
+    }
+
+    #[test]
+    fn missing_nodes_have_no_position() {
+        assert_eq!(locate(SOURCE, "/fields/score"), None);
+        assert_eq!(locate(SOURCE, "/name/0"), None);
+        let err = SchemaError::new("Invalid").in_node("score").in_file(Path::new("Player.quops"), SOURCE);
+        assert_eq!(err.to_string(), "Player.quops at /score: Invalid");
+    }
+
+    #[test]
+    fn errors_in_dependencies_keep_their_own_file() {
+        let dir = std::env::temp_dir().join(format!("quops_errors_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Outer.quops"), r#"{
+  "name": "Outer",
+  "type": "record",
+  "fields": { "inner": "Inner" },
+  "dependencies": ["Inner"]
+}"#).unwrap();
+        std::fs::write(dir.join("Inner.quops"), r#"{
+  "name": "Inner",
+  "type": "record",
+  "fields": {
+    "n": { "type": "int", "min": "0" }
+  }
+}"#).unwrap();
+
+        let err = Schema::parse_from_file(dir.join("Outer.quops")).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(err.file, Some(dir.join("Inner.quops")));
+        assert_eq!(err.pointer, "/fields/n/min");
+        assert_eq!(err.position, Some((5, 34)));
+    }
+}
@@ -2,32 +2,36 @@ pub trait FieldTrait {
     fn bits(&self) -> u32;
     fn name(&self) -> &str;
     fn nullable(&self) -> bool;
-    fn is_primitive(&self) -> bool;
 }
 
-#[derive(Default, Eq, PartialEq, Clone, Debug, Hash)]
+#[derive(Default, PartialEq, Clone, Debug)]
 pub struct IntField {
     name: String,
     bits: u8,
-    pub min: Option<i32>,
-    pub max: Option<i32>,
+    pub min: Option<i128>,
+    pub max: Option<i128>,
     nullable: bool,
 }
 
 impl IntField {
-    pub fn new(name: &str, min: Option<i32>, max: Option<i32>, nullable: bool) -> Result<Self, String> {
-        let bits = match (min, max) {
+    pub fn new(name: &str, min: Option<i128>, max: Option<i128>, nullable: bool) -> Result<Self, String> {
+        let value_bits = match (min, max) {
             (Some(min), Some(max)) => {
                 if min > max {
                     return Err("Minimum value cannot be greater than maximum value".to_string());
                 }
-                32 - (max - min + 1).leading_zeros() as u8
+                let span = (max - min) as u128;
+                if span > u64::MAX as u128 {
+                    return Err(format!("Range [{}, {}] spans more than 64 bits", min, max));
+                }
+                (128 - span.leading_zeros()) as u8
             },
-            _ => 5,
-        } + nullable as u8;
+            // Width prefix of a value with at least one open end, which is at most 64 bits wide
+            _ => 6,
+        };
         Ok(IntField {
             name: name.to_string(),
-            bits,
+            bits: value_bits + nullable as u8,
             min,
             max,
             nullable,
@@ -35,7 +39,7 @@ impl IntField {
     }
 }
 
-#[derive(Default, Eq, PartialEq, Clone, Debug, Hash)]
+#[derive(Default, PartialEq, Clone, Debug)]
 pub struct BooleanField {
     name: String,
     bits: u8,
@@ -52,11 +56,74 @@ impl BooleanField {
     }
 }
 
-#[derive(Default, Eq, PartialEq, Clone, Debug, Hash)]
+#[derive(Default, PartialEq, Clone, Debug)]
+pub struct FloatField {
+    name: String,
+    bits: u8,
+    pub width: u8,
+    nullable: bool,
+}
+
+impl FloatField {
+    pub fn new(name: &str, width: u8, nullable: bool) -> Self {
+        debug_assert!(width == 32 || width == 64, "Float width must be 32 or 64. Found: {}", width);
+        FloatField {
+            name: name.to_string(),
+            bits: width + nullable as u8,
+            width,
+            nullable,
+        }
+    }
+}
+
+/// A float stored as the number of `precision` steps above `min`.
+#[derive(Default, PartialEq, Clone, Debug)]
+pub struct QuantizedFloatField {
+    name: String,
+    bits: u8,
+    pub min: f64,
+    pub max: f64,
+    pub precision: f64,
+    pub steps: u64,
+    pub clamp: bool,
+    nullable: bool,
+}
+
+impl QuantizedFloatField {
+    pub fn new(name: &str, min: f64, max: f64, precision: f64, clamp: bool, nullable: bool) -> Result<Self, String> {
+        if !min.is_finite() || !max.is_finite() || min > max {
+            return Err(format!("Invalid range: min = {}, max = {}", min, max));
+        }
+        if !precision.is_finite() || precision <= 0.0 {
+            return Err(format!("Precision must be a positive number, got {}", precision));
+        }
+
+        let steps = ((max - min) / precision).round();
+        if steps > u64::MAX as f64 {
+            return Err(format!("Range [{}, {}] with precision {} needs more than 64 bits", min, max, precision));
+        }
+        let steps = steps as u64;
+        let bits = IntField::new(name, Some(0), Some(steps as i128), nullable)?.bits;
+
+        Ok(QuantizedFloatField {
+            name: name.to_string(),
+            bits,
+            min,
+            max,
+            precision,
+            steps,
+            clamp,
+            nullable,
+        })
+    }
+}
+
+#[derive(Default, PartialEq, Clone, Debug)]
 pub struct BytesField {
     name: String,
     bits: u8,
     pub max_length: Option<u32>,
+    pub length: Option<u32>,
     nullable: bool,
 }
 
@@ -67,6 +134,41 @@ impl BytesField {
             None => 5,
         } + nullable as u8;
         BytesField {
+            name: name.to_string(),
+            bits,
+            max_length,
+            length: None,
+            nullable,
+        }
+    }
+
+    /// Bytes of a fixed length, written without a length prefix.
+    pub fn fixed(name: &str, length: u32, nullable: bool) -> Self {
+        BytesField {
+            name: name.to_string(),
+            bits: nullable as u8,
+            max_length: Some(length),
+            length: Some(length),
+            nullable,
+        }
+    }
+}
+
+#[derive(Default, PartialEq, Clone, Debug)]
+pub struct StringField {
+    name: String,
+    bits: u8,
+    pub max_length: Option<u32>,
+    nullable: bool,
+}
+
+impl StringField {
+    pub fn new(name: &str, max_length: Option<u32>, nullable: bool) -> Self {
+        let bits = match max_length {
+            Some(length) => 32 - length.leading_zeros() as u8,
+            None => 5,
+        } + nullable as u8;
+        StringField {
             name: name.to_string(),
             bits,
             max_length,
@@ -75,30 +177,30 @@ impl BytesField {
     }
 }
 
-#[derive(Default, Eq, PartialEq, Clone, Debug, Hash)]
+#[derive(Default, PartialEq, Clone, Debug)]
 pub struct EnumField {
     name: String,
     bits: u8,
-    pub variants: u8,
     nullable: bool,
 }
 
 impl EnumField {
-    pub fn new(name: &str, variants: u8, nullable: bool) -> Self {
+    pub fn new(name: &str, id_bits: u8, nullable: bool) -> Self {
         EnumField {
             name: name.to_string(),
-            bits: 8 - variants.leading_zeros() as u8 + nullable as u8,
-            variants,
+            bits: id_bits + nullable as u8,
             nullable,
         }
     }
 }
 
-#[derive(Default, Eq, PartialEq, Clone, Debug, Hash)]
+#[derive(Default, PartialEq, Clone, Debug)]
 pub struct RecordField {
     name: String,
     bits: u32,
     pub fields: Vec<Field>,
+    /// Refers back to a record that is still being parsed, so its fields are not known here.
+    pub recursive: bool,
     nullable: bool,
 }
 
@@ -111,16 +213,28 @@ impl RecordField {
             name: name.to_string(),
             bits,
             fields,
+            recursive: false,
+            nullable,
+        }
+    }
+
+    pub fn recursive(name: &str, nullable: bool) -> Self {
+        RecordField {
+            name: name.to_string(),
+            bits: nullable as u32,
+            fields: Vec::new(),
+            recursive: true,
             nullable,
         }
     }
 }
 
-#[derive(Eq, PartialEq, Clone, Debug, Hash)]
+#[derive(PartialEq, Clone, Debug)]
 pub struct ArrayField {
     name: String,
     bits: u8,
     max_length: u32,
+    pub length: Option<u32>,
     pub items_field: Box<Field>,
     nullable: bool,
 }
@@ -132,20 +246,145 @@ impl ArrayField {
             name: name.to_string(),
             bits,
             max_length,
+            length: None,
+            items_field: Box::new(field),
+            nullable,
+        }
+    }
+
+    /// An array of a fixed length, written without a length prefix.
+    pub fn fixed(name: &str, length: u32, field: Field, nullable: bool) -> Self {
+        ArrayField {
+            name: name.to_string(),
+            bits: nullable as u8,
+            max_length: length,
+            length: Some(length),
             items_field: Box::new(field),
             nullable,
         }
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[derive(Default, PartialEq, Clone, Debug)]
+pub struct UnionField {
+    name: String,
+    bits: u8,
+    nullable: bool,
+}
+
+impl UnionField {
+    /// Only the tag has a fixed width, the payload depends on the variant.
+    pub fn new(name: &str, variants: usize, nullable: bool) -> Self {
+        UnionField {
+            name: name.to_string(),
+            bits: union_tag_bits(variants) + nullable as u8,
+            nullable,
+        }
+    }
+}
+
+/// The number of bits needed to tell `variants` variants apart.
+pub fn union_tag_bits(variants: usize) -> u8 {
+    (usize::BITS - variants.saturating_sub(1).leading_zeros()) as u8
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct MapField {
+    name: String,
+    bits: u8,
+    pub max_length: u32,
+    pub keys_field: Box<Field>,
+    pub values_field: Box<Field>,
+    nullable: bool,
+}
+
+impl MapField {
+    pub fn new(name: &str, max_length: u32, keys_field: Field, values_field: Field, nullable: bool) -> Self {
+        let bits = (32 - max_length.leading_zeros()) as u8 + nullable as u8;
+        MapField {
+            name: name.to_string(),
+            bits,
+            max_length,
+            keys_field: Box::new(keys_field),
+            values_field: Box::new(values_field),
+            nullable,
+        }
+    }
+}
+
+/// A field with a fixed value, which takes no bits unless it is `checked` against the wire.
+#[derive(Default, PartialEq, Clone, Debug)]
+pub struct ConstField {
+    name: String,
+    bits: u8,
+    pub value: u64,
+    pub checked: bool,
+}
+
+impl ConstField {
+    pub fn new(name: &str, value: u64, check_bits: Option<u8>) -> Self {
+        ConstField {
+            name: name.to_string(),
+            bits: check_bits.unwrap_or(0),
+            value,
+            checked: check_bits.is_some(),
+        }
+    }
+}
+
+/// The value a field takes when it is left out of the wire.
+#[derive(PartialEq, Clone, Debug)]
+pub enum DefaultValue {
+    Int(i128),
+    Bool(bool),
+    Float(f64),
+    String(String),
+    /// The id of an enum variant.
+    Enum(u64),
+}
+
+/// Wraps a field with a default value, which is written as a single cleared bit instead of the full value.
+///
+/// Payloads written before the field was added only read back when it was appended to the record
+/// that is decoded as the message. Fields added to nested records change the wire format.
+#[derive(PartialEq, Clone, Debug)]
+pub struct DefaultField {
+    pub field: Box<Field>,
+    pub value: DefaultValue,
+}
+
+impl DefaultField {
+    pub fn new(field: Field, value: DefaultValue) -> Self {
+        DefaultField {
+            field: Box::new(field),
+            value,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum Field {
     Int(IntField),
     Boolean(BooleanField),
+    Float(FloatField),
+    QuantizedFloat(QuantizedFloatField),
     Bytes(BytesField),
+    String(StringField),
     Enum(EnumField),
     Record(RecordField),
+    Union(UnionField),
     Array(ArrayField),
+    Map(MapField),
+    Default(DefaultField),
+    Const(ConstField),
+}
+
+impl Field {
+    /// The width of the value itself, without the presence bit of a nullable field. Code generation
+    /// writes and reads the presence bit separately, so values use only this width.
+    pub fn value_bits(&self) -> u32 {
+        self.bits() - self.nullable() as u32
+    }
 }
 
 impl FieldTrait for Field {
@@ -153,10 +392,17 @@ impl FieldTrait for Field {
         match self {
             Field::Int(field) => field.bits as u32,
             Field::Boolean(field) => field.bits as u32,
+            Field::Float(field) => field.bits as u32,
+            Field::QuantizedFloat(field) => field.bits as u32,
             Field::Bytes(field) => field.bits as u32,
+            Field::String(field) => field.bits as u32,
             Field::Enum(field) => field.bits as u32,
             Field::Record(field) => field.bits,
+            Field::Union(field) => field.bits as u32,
             Field::Array(field) => field.bits as u32,
+            Field::Map(field) => field.bits as u32,
+            Field::Default(field) => field.field.bits() + 1,
+            Field::Const(field) => field.bits as u32,
         }
     }
 
@@ -164,10 +410,17 @@ impl FieldTrait for Field {
         match self {
             Field::Int(field) => &field.name,
             Field::Boolean(field) => &field.name,
+            Field::Float(field) => &field.name,
+            Field::QuantizedFloat(field) => &field.name,
             Field::Bytes(field) => &field.name,
+            Field::String(field) => &field.name,
             Field::Enum(field) => &field.name,
             Field::Record(field) => &field.name,
+            Field::Union(field) => &field.name,
             Field::Array(field) => &field.name,
+            Field::Map(field) => &field.name,
+            Field::Default(field) => field.field.name(),
+            Field::Const(field) => &field.name,
         }
     }
 
@@ -175,17 +428,17 @@ impl FieldTrait for Field {
         match self {
             Field::Int(field) => field.nullable,
             Field::Boolean(field) => field.nullable,
+            Field::Float(field) => field.nullable,
+            Field::QuantizedFloat(field) => field.nullable,
             Field::Bytes(field) => field.nullable,
+            Field::String(field) => field.nullable,
             Field::Enum(field) => field.nullable,
             Field::Record(field) => field.nullable,
+            Field::Union(field) => field.nullable,
             Field::Array(field) => field.nullable,
-        }
-    }
-
-    fn is_primitive(&self) -> bool {
-        match self {
-            Field::Int(_) | Field::Boolean(_) | Field::Bytes(_) | Field::Enum(_) => true,
-            Field::Record(_) | Field::Array(_) => false,
+            Field::Map(field) => field.nullable,
+            Field::Default(field) => field.field.nullable(),
+            Field::Const(_) => false,
         }
     }
 }
\ No newline at end of file
@@ -1,10 +1,14 @@
 use std::collections::HashMap;
-use crate::field::{ArrayField, BooleanField, BytesField, EnumField, Field, IntField, FieldTrait, RecordField};
+use crate::errors::SchemaError;
+use crate::field::{ArrayField, BooleanField, BytesField, ConstField, DefaultField, DefaultValue, EnumField, Field, FloatField, IntField, FieldTrait, MapField, QuantizedFloatField, RecordField, StringField, UnionField, union_tag_bits};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RecordSchema {
+    /// The fields in the order they are written on the wire, see `ordered_fields`.
     pub fields: Vec<Field>,
-    dependencies: HashMap<String, Schema>
+    dependencies: HashMap<String, Schema>,
+    /// Stands in for a record that is still being parsed further up the dependency chain.
+    recursive: bool,
 }
 
 impl RecordSchema {
@@ -12,143 +16,647 @@ impl RecordSchema {
         self.fields.iter().map(|f| f.bits()).sum()
     }
 
-    pub fn parse_field(&self, name: &str, value: &serde_json::Value) -> Result<Field, String> {
+    /// Builds a field referring to the schema `ty` listed in the dependencies.
+    fn parse_dependency_field(&self, name: &str, ty: &str, nullable: bool) -> Option<Field> {
+        self.dependencies.get(ty).map(|schema| schema.to_field(name, nullable))
+    }
+
+    /// Parses a schema declared inline as a field type. It may refer to the same dependencies as this record.
+    fn parse_inline(&self, value: &serde_json::Value) -> Result<Schema, SchemaError> {
+        Schema::parse_value(value, std::path::Path::new(""), &mut Loading::default(), Some(&self.dependencies))
+    }
+
+    /// Parses a union variant, either a bare name or an object with a `payload` field type or a `fields` object.
+    fn parse_union_variant(&self, value: &serde_json::Value) -> Result<UnionVariant, SchemaError> {
+        if let Some(name) = value.as_str() {
+            return Ok(UnionVariant { name: name.to_string(), payload: UnionPayload::None });
+        }
+        let map = value.as_object().ok_or("Variant is not a string or an object")?;
+        let name = map.get("name").and_then(|v| v.as_str())
+            .ok_or_else(|| SchemaError::new("Variant 'name' is not a string").in_node("name"))?;
+        let payload = match (map.get("payload"), map.get("fields")) {
+            (Some(_), Some(_)) => return Err(format!("Variant '{}' cannot have both 'payload' and 'fields'", name).into()),
+            (Some(payload), None) => UnionPayload::Field(self.parse_field(name, payload).map_err(|e| e.in_node("payload"))?),
+            (None, Some(fields)) => {
+                let fields = fields.as_object()
+                    .ok_or_else(|| SchemaError::new(format!("Variant '{}' fields are not an object", name)).in_node("fields"))?;
+                let mut payload_fields = Vec::new();
+                for (field_name, field_value) in ordered_fields(fields).map_err(|e| e.in_node("fields"))? {
+                    let field = self.parse_field(field_name, field_value)
+                        .map_err(|e| e.in_node(field_name).in_node("fields"))?;
+                    payload_fields.push(field);
+                }
+                UnionPayload::Fields(payload_fields)
+            },
+            (None, None) => UnionPayload::None,
+        };
+        Ok(UnionVariant { name: name.to_string(), payload })
+    }
+
+    pub fn parse_field(&self, name: &str, value: &serde_json::Value) -> Result<Field, SchemaError> {
+        let field = self.parse_field_type(name, value)?;
+        match value.get("default") {
+            Some(default) => self.parse_default(field, value, default)
+                .map_err(|e| SchemaError::new(e).in_node("default")),
+            None => Ok(field),
+        }
+    }
+
+    /// Checks `default` against the field it belongs to and wraps the field with it.
+    fn parse_default(&self, field: Field, value: &serde_json::Value, default: &serde_json::Value) -> Result<Field, String> {
+        let name = field.name().to_string();
+        if field.nullable() {
+            return Err(format!("Field '{}' is nullable and cannot have a default", name));
+        }
+        let invalid = || format!("Default {} does not match the type of field '{}'", default, name);
+
+        let default_value = match &field {
+            Field::Int(int_field) => {
+                let default = json_to_i128(default).ok_or_else(invalid)?;
+                if int_field.min.is_some_and(|min| default < min) || int_field.max.is_some_and(|max| default > max) {
+                    return Err(format!("Default {} of field '{}' is out of range", default, name));
+                }
+                DefaultValue::Int(default)
+            },
+            Field::Boolean(_) => DefaultValue::Bool(default.as_bool().ok_or_else(invalid)?),
+            Field::Float(_) => {
+                let default = default.as_f64().filter(|d| d.is_finite()).ok_or_else(invalid)?;
+                DefaultValue::Float(default)
+            },
+            Field::QuantizedFloat(float_field) => {
+                let default = default.as_f64().filter(|d| d.is_finite()).ok_or_else(invalid)?;
+                if default < float_field.min || default > float_field.max {
+                    return Err(format!("Default {} of field '{}' is out of range", default, name));
+                }
+                DefaultValue::Float(default)
+            },
+            Field::String(string_field) => {
+                let default = default.as_str().ok_or_else(invalid)?;
+                if string_field.max_length.is_some_and(|max_length| default.len() > max_length as usize) {
+                    return Err(format!("Default of field '{}' is longer than its maxLength", name));
+                }
+                DefaultValue::String(default.to_string())
+            },
+            Field::Enum(_) => {
+                let variant_name = default.as_str().ok_or_else(invalid)?;
+                let enum_schema = match value.get("type") {
+                    Some(serde_json::Value::String(ty)) => self.dependencies.get(ty).cloned(),
+                    Some(inline) => self.parse_inline(inline).ok(),
+                    None => None,
+                };
+                let Some(Schema::Enum(enum_schema)) = enum_schema else {
+                    return Err(invalid());
+                };
+                let variant = enum_schema.variants.iter()
+                    .find(|v| v.name == variant_name)
+                    .ok_or(format!("Default '{}' of field '{}' is not a variant of the enum", variant_name, name))?;
+                DefaultValue::Enum(variant.id)
+            },
+            _ => return Err(format!("Field '{}' cannot have a default, only ints, bools, floats, strings and enums can", name)),
+        };
+
+        Ok(Field::Default(DefaultField::new(field, default_value)))
+    }
+
+    fn parse_field_type(&self, name: &str, value: &serde_json::Value) -> Result<Field, SchemaError> {
         if let Some(ty) = value.as_str() {
             match ty {
                 "int" => Ok(Field::Int(IntField::new(name, None, None, false)?)),
                 "bool" => Ok(Field::Boolean(BooleanField::new(name, false))),
+                "float32" => Ok(Field::Float(FloatField::new(name, 32, false))),
+                "float64" => Ok(Field::Float(FloatField::new(name, 64, false))),
                 "bytes" => Ok(Field::Bytes(BytesField::new(name, None, false))),
-                "array" => Err(format!("Field '{}' is an array but no schema provided for it", name)),
-                _ => {
-                    if let Some(dep_schema) = self.dependencies.get(ty) {
-                        match dep_schema {
-                            Schema::Record(record_schema) => {
-                                Ok(Field::Record(RecordField::new(name, record_schema.fields.clone(), false)))
-                            },
-                            Schema::Enum(enum_schema) => {
-                                Ok(Field::Enum(EnumField::new(name, enum_schema.variants.len() as u8, false)))
-                            }
-                        }
-                    } else {
-                        Err(format!("Unsupported field type: {}", ty))
-                    }
-                }
+                "string" => Ok(Field::String(StringField::new(name, None, false))),
+                "array" => Err(format!("Field '{}' is an array but no schema provided for it", name).into()),
+                "map" => Err(format!("Field '{}' is a map but no schema provided for it", name).into()),
+                _ => self.parse_dependency_field(name, ty, false)
+                    .ok_or_else(|| format!("Unsupported field type: {}", ty).into()),
             }
         } else if let Some(map) = value.as_object() {
-            let ty = map.get("type").and_then(|v| v.as_str()).expect("Field 'type' is not a string");
+            if let Some(inline) = map.get("type").filter(|ty| ty.is_object()) {
+                let nullable = map.get("nullable").and_then(|v| v.as_bool()).unwrap_or(false);
+                let schema = self.parse_inline(inline).map_err(|e| e.in_node("type"))?;
+                return Ok(schema.to_field(name, nullable));
+            }
+            let ty = map.get("type").and_then(|v| v.as_str())
+                .ok_or_else(|| SchemaError::new(format!("Field '{}' type is not a string or an object", name)).in_node("type"))?;
             let nullable = map.get("nullable").and_then(|v| v.as_bool()).unwrap_or(false);
             match ty {
                 "int" => {
-                    let min = map.get("min").and_then(|v| v.as_i64().map(|v| v as i32));
-                    let max = map.get("max").and_then(|v| v.as_i64().map(|v| v as i32));
+                    let bound = |key: &str| match map.get(key) {
+                        Some(value) => json_to_i128(value).map(Some).ok_or_else(|| {
+                            SchemaError::new(format!("Int field '{}' {} must be an integer between {} and {}", name, key, i64::MIN, u64::MAX)).in_node(key)
+                        }),
+                        None => Ok(None),
+                    };
+                    let min = bound("min")?;
+                    let max = bound("max")?;
 
                     if min.is_some() && max.is_some() && min > max {
-                        return Err(format!("Invalid range: min = {:?}, max = {:?}", min, max));
+                        return Err(format!("Invalid range: min = {:?}, max = {:?}", min, max).into());
                     }
 
                     Ok(Field::Int(IntField::new(name, min, max, nullable)?))
                 },
                 "bool" => Ok(Field::Boolean(BooleanField::new(name, nullable))),
+                "float32" => Ok(Field::Float(FloatField::new(name, 32, nullable))),
+                "float64" => Ok(Field::Float(FloatField::new(name, 64, nullable))),
+                "float" => {
+                    let min = map.get("min").and_then(|v| v.as_f64()).ok_or(format!("Float field '{}' must have a numeric 'min'", name))?;
+                    let max = map.get("max").and_then(|v| v.as_f64()).ok_or(format!("Float field '{}' must have a numeric 'max'", name))?;
+                    let precision = map.get("precision").and_then(|v| v.as_f64()).ok_or(format!("Float field '{}' must have a numeric 'precision'", name))?;
+                    let clamp = map.get("clamp").and_then(|v| v.as_bool()).unwrap_or(false);
+                    Ok(Field::QuantizedFloat(QuantizedFloatField::new(name, min, max, precision, clamp, nullable)?))
+                },
                 "bytes" => {
                     let max_length = map.get("maxLength")
                         .and_then(|v| v.as_u64())
                         .map(|v| v as u32);
-                    Ok(Field::Bytes(BytesField::new(name, max_length, nullable)))
+                    let length = map.get("length")
+                        .and_then(|v| v.as_u64())
+                        .map(|v| v as u32);
+                    match (length, max_length) {
+                        (Some(_), Some(_)) => Err(format!("Bytes field '{}' cannot have both 'length' and 'maxLength'", name).into()),
+                        (Some(length), None) => Ok(Field::Bytes(BytesField::fixed(name, length, nullable))),
+                        (None, _) => Ok(Field::Bytes(BytesField::new(name, max_length, nullable))),
+                    }
+                },
+                "string" => {
+                    let max_length = map.get("maxLength")
+                        .and_then(|v| v.as_u64())
+                        .map(|v| v as u32);
+                    Ok(Field::String(StringField::new(name, max_length, nullable)))
                 },
                 "array" => {
                     let max_length = map.get("maxLength")
                         .and_then(|v| v.as_u64())
-                        .map(|v| v as u32)
-                        .unwrap_or_else(|| u32::MAX);
-                    let items_type = map.get("items").expect("Array field must have the 'items' field");
-                    let items_field = self.parse_field(name, items_type)?;
-                    Ok(Field::Array(ArrayField::new(name, max_length, items_field, nullable)))
+                        .map(|v| v as u32);
+                    let length = map.get("length")
+                        .and_then(|v| v.as_u64())
+                        .map(|v| v as u32);
+                    let items_type = map.get("items").ok_or(format!("Array field '{}' must have the 'items' field", name))?;
+                    let items_field = self.parse_field(name, items_type).map_err(|e| e.in_node("items"))?;
+                    match (length, max_length) {
+                        (Some(_), Some(_)) => Err(format!("Array field '{}' cannot have both 'length' and 'maxLength'", name).into()),
+                        (Some(length), None) => Ok(Field::Array(ArrayField::fixed(name, length, items_field, nullable))),
+                        (None, _) => Ok(Field::Array(ArrayField::new(name, max_length.unwrap_or(u32::MAX), items_field, nullable))),
+                    }
                 }
-                _ => {
-                    if let Some(dep_schema) = self.dependencies.get(ty) {
-                        match dep_schema {
-                            Schema::Record(record_schema) => {
-                                Ok(Field::Record(RecordField::new(name, record_schema.fields.clone(), nullable)))
-                            },
-                            Schema::Enum(enum_schema) => {
-                                Ok(Field::Enum(EnumField::new(name, enum_schema.variants.len() as u8, nullable)))
-                            },
+                "const" => {
+                    let value = map.get("value").and_then(|v| v.as_u64())
+                        .ok_or(format!("Const field '{}' must have an unsigned integer 'value'", name))?;
+                    let check = map.get("check").and_then(|v| v.as_bool()).unwrap_or(false);
+                    let check_bits = if check {
+                        let min_bits = (64 - value.leading_zeros()).max(1) as u8;
+                        let bits = match map.get("bits") {
+                            Some(bits) => bits.as_u64().filter(|&b| (1..=64).contains(&b))
+                                .ok_or(format!("Const field '{}' must have 'bits' between 1 and 64", name))? as u8,
+                            None => min_bits,
+                        };
+                        if bits < min_bits {
+                            return Err(format!("Const field '{}' has value {}, which does not fit in {} bits", name, value, bits).into());
                         }
+                        Some(bits)
                     } else {
-                        Err(format!("Field '{}' is not a valid type or object", name))
-                    }
+                        None
+                    };
+                    Ok(Field::Const(ConstField::new(name, value, check_bits)))
                 }
+                "map" => {
+                    let max_length = map.get("maxLength")
+                        .and_then(|v| v.as_u64())
+                        .map(|v| v as u32)
+                        .unwrap_or(u32::MAX);
+                    let keys_type = map.get("keys").ok_or(format!("Map field '{}' must have the 'keys' field", name))?;
+                    let values_type = map.get("values").ok_or(format!("Map field '{}' must have the 'values' field", name))?;
+                    let keys_field = self.parse_field(name, keys_type).map_err(|e| e.in_node("keys"))?;
+                    let values_field = self.parse_field(name, values_type).map_err(|e| e.in_node("values"))?;
+                    Ok(Field::Map(MapField::new(name, max_length, keys_field, values_field, nullable)))
+                }
+                _ => self.parse_dependency_field(name, ty, nullable)
+                    .ok_or_else(|| format!("Field '{}' is not a valid type or object", name).into()),
             }
         } else {
-            Err(format!("Field '{}' is not a valid type or object", name))
+            Err(format!("Field '{}' is not a valid type or object", name).into())
+        }
+    }
+}
+
+/// Returns the fields of a record in wire order. This is the order the fields are declared in, unless every
+/// field has an explicit `index`, in which case the fields are ordered by it.
+fn ordered_fields(fields: &serde_json::Map<String, serde_json::Value>) -> Result<Vec<(&String, &serde_json::Value)>, SchemaError> {
+    let mut indexed = Vec::new();
+    for (name, value) in fields {
+        if let Some(index) = value.get("index") {
+            let index = index.as_u64()
+                .ok_or_else(|| SchemaError::new(format!("Field '{}' index is not an unsigned integer", name)).in_node("index").in_node(name))?;
+            if let Some((other, _, _)) = indexed.iter().find(|(_, i, _)| *i == index) {
+                return Err(SchemaError::new(format!("Fields '{}' and '{}' have the same index {}", other, name, index)).in_node("index").in_node(name));
+            }
+            indexed.push((name, index, value));
         }
     }
+
+    if indexed.is_empty() {
+        return Ok(fields.iter().collect());
+    }
+    if let Some(name) = fields.keys().find(|name| !indexed.iter().any(|(n, _, _)| n == name)) {
+        return Err(SchemaError::new(format!("Field '{}' has no index, but other fields do. Either all fields or none must have one", name)).in_node(name));
+    }
+    indexed.sort_by_key(|(_, index, _)| *index);
+    Ok(indexed.into_iter().map(|(name, _, value)| (name, value)).collect())
+}
+
+/// Reads an integer bound, which may lie anywhere in `i64::MIN..=u64::MAX`.
+fn json_to_i128(value: &serde_json::Value) -> Option<i128> {
+    value.as_i64().map(|v| v as i128).or_else(|| value.as_u64().map(|v| v as i128))
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub struct EnumVariant {
+    pub name: String,
+    pub id: u64,
+}
+
+#[derive(Debug, Clone)]
 pub struct EnumSchema {
-    pub variants: Vec<String>,
+    pub variants: Vec<EnumVariant>,
+    /// The variant that holds ids not assigned to any other variant. Requires `bits` to be set.
+    pub unknown: Option<String>,
+    /// A fixed id width, so that newer variants keep the wire format readable by older schemas.
+    bits: Option<u8>,
+}
+
+impl EnumSchema {
+    pub fn max_id(&self) -> u64 {
+        self.variants.iter().map(|v| v.id).max().unwrap_or(0)
+    }
+
+    pub fn id_bits(&self) -> u8 {
+        self.bits.unwrap_or((64 - self.max_id().leading_zeros()) as u8)
+    }
+
+    pub fn has_fixed_bits(&self) -> bool {
+        self.bits.is_some()
+    }
+}
+
+/// The data carried by a union variant.
+#[derive(Debug, Clone)]
+pub enum UnionPayload {
+    /// A unit variant such as `Ping`.
+    None,
+    /// A tuple variant with a single field such as `Joined(Player)`.
+    Field(Field),
+    /// A struct variant such as `Left { id: u32 }`.
+    Fields(Vec<Field>),
+}
+
+#[derive(Debug, Clone)]
+pub struct UnionVariant {
+    pub name: String,
+    pub payload: UnionPayload,
+}
+
+#[derive(Debug, Clone)]
+pub struct UnionSchema {
+    pub variants: Vec<UnionVariant>,
+}
+
+impl UnionSchema {
+    pub fn tag_bits(&self) -> u8 {
+        union_tag_bits(self.variants.len())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Schema {
     Record(RecordSchema),
-    Enum(EnumSchema)
+    Enum(EnumSchema),
+    Union(UnionSchema),
+}
+
+/// Returns the directory that schema paths are resolved against: the `QUOPS_SCHEMA_ROOT` environment variable if set,
+/// else `package.metadata.quops.schema-root` in the `Cargo.toml` of the crate being compiled, else that crate's directory.
+/// A relative root is relative to the crate's directory.
+pub fn schema_root() -> std::path::PathBuf {
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR").map(std::path::PathBuf::from).unwrap_or_default();
+    let root = std::env::var_os("QUOPS_SCHEMA_ROOT").map(std::path::PathBuf::from)
+        .or_else(|| manifest_schema_root(&manifest_dir));
+    match root {
+        Some(root) => manifest_dir.join(root),
+        None => manifest_dir,
+    }
+}
+
+fn manifest_schema_root(manifest_dir: &std::path::Path) -> Option<std::path::PathBuf> {
+    let manifest = std::fs::read_to_string(manifest_dir.join("Cargo.toml")).ok()?;
+    let manifest = manifest.parse::<toml::Table>().ok()?;
+    let root = manifest.get("package")?.get("metadata")?.get("quops")?.get("schema-root")?.as_str()?;
+    Some(std::path::PathBuf::from(root))
+}
+
+/// Resolves a `#[schema(path = ...)]` path against the schema root. Absolute paths are kept as they are.
+pub fn resolve_schema_path(path: &str) -> std::path::PathBuf {
+    schema_root().join(path)
+}
+
+/// Tracks the schema files involved in parsing a schema.
+#[derive(Default)]
+struct Loading {
+    /// The schema files currently being parsed, from the root down, along with their schema type.
+    stack: Vec<(std::path::PathBuf, String)>,
+    /// Every schema file read, including dependencies.
+    files: Vec<std::path::PathBuf>,
+}
+
+impl Loading {
+    fn read_file(&mut self, file_path: &std::path::Path) -> Result<(String, serde_json::Value), SchemaError> {
+        self.files.push(file_path.to_path_buf());
+        Schema::read_file(file_path)
+    }
+}
+
+/// Fails if a record contains itself through fields that always hold a value, as such a record
+/// would have infinite size. Nullable, array and map fields can end the recursion.
+fn check_finite_size(fields: &[Field], path: &mut Vec<String>) -> Result<(), SchemaError> {
+    for field in fields {
+        let Field::Record(record_field) = field else { continue };
+        if field.nullable() {
+            continue;
+        }
+        path.push(field.name().to_string());
+        if record_field.recursive {
+            let message = format!("Record has infinite size, since it contains itself through '{}'. Make one of these fields nullable or wrap it in an array", path.join("."));
+            return Err(SchemaError::new(message).in_node(&path[0]).in_node("fields"));
+        }
+        check_finite_size(&record_field.fields, path)?;
+        path.pop();
+    }
+    Ok(())
+}
+
+/// Returns the name of the Rust type for an inline schema: its `name`, or else the field name in PascalCase.
+fn inline_type_name(value: &serde_json::Value, field_name: &str) -> String {
+    if let Some(name) = value.get("name").and_then(|v| v.as_str()) {
+        return name.to_string();
+    }
+    field_name.split('_')
+        .flat_map(|part| {
+            let mut chars = part.chars();
+            chars.next().into_iter().flat_map(char::to_uppercase).chain(chars)
+        })
+        .collect()
+}
+
+/// Searches the field types of a schema, including nested inline schemas, for the inline schema named `name`.
+/// Returns the schema along with the path of JSON keys leading to it.
+fn find_inline<'a>(schema_value: &'a serde_json::Value, name: &str) -> Option<(&'a serde_json::Value, Vec<String>)> {
+    let mut field_types: Vec<(&str, Vec<String>, &serde_json::Value)> = Vec::new();
+    if let Some(fields) = schema_value.get("fields").and_then(|v| v.as_object()) {
+        field_types.extend(fields.iter().map(|(field_name, v)| (field_name.as_str(), vec!["fields".to_string(), field_name.clone()], v)));
+    }
+    for (i, variant) in schema_value.get("variants").and_then(|v| v.as_array()).into_iter().flatten().enumerate() {
+        let variant_name = variant.get("name").and_then(|v| v.as_str()).unwrap_or_default();
+        if let Some(payload) = variant.get("payload") {
+            field_types.push((variant_name, vec!["variants".to_string(), i.to_string(), "payload".to_string()], payload));
+        }
+        if variant.get("fields").is_some() && let Some((found, path)) = find_inline(variant, name) {
+            return Some((found, [vec!["variants".to_string(), i.to_string()], path].concat()));
+        }
+    }
+
+    while let Some((field_name, path, value)) = field_types.pop() {
+        let Some(map) = value.as_object() else { continue };
+        for key in ["items", "keys", "values"] {
+            if let Some(inner) = map.get(key) {
+                field_types.push((field_name, [path.clone(), vec![key.to_string()]].concat(), inner));
+            }
+        }
+        let Some(inline) = map.get("type").filter(|ty| ty.is_object()) else { continue };
+        let path = [path, vec!["type".to_string()]].concat();
+        if inline_type_name(inline, field_name) == name {
+            return Some((inline, path));
+        }
+        if let Some((found, inner_path)) = find_inline(inline, name) {
+            return Some((found, [path, inner_path].concat()));
+        }
+    }
+    None
 }
 
 impl Schema {
-    pub fn parse_from_file(file_path: std::path::PathBuf) -> Result<Self, String> {
-        let schema_contents = std::fs::read_to_string(&file_path)
-            .expect("Failed to read schema file");
+    /// Builds a field holding a value of this schema.
+    fn to_field(&self, name: &str, nullable: bool) -> Field {
+        match self {
+            Schema::Record(record_schema) if record_schema.recursive => {
+                Field::Record(RecordField::recursive(name, nullable))
+            },
+            Schema::Record(record_schema) => {
+                Field::Record(RecordField::new(name, record_schema.fields.clone(), nullable))
+            },
+            Schema::Enum(enum_schema) => {
+                Field::Enum(EnumField::new(name, enum_schema.id_bits(), nullable))
+            },
+            Schema::Union(union_schema) => {
+                Field::Union(UnionField::new(name, union_schema.variants.len(), nullable))
+            },
+        }
+    }
+
+    fn parse_dependencies(schema_value: &serde_json::Value, file_path: &std::path::Path, loading: &mut Loading) -> Result<HashMap<String, Schema>, SchemaError> {
+        let file_path_parent = file_path.parent().unwrap_or(std::path::Path::new(""));
+        let mut dependencies = HashMap::new();
+        let Some(deps) = schema_value.get("dependencies") else {
+            return Ok(dependencies);
+        };
+        let deps = deps.as_array()
+            .ok_or_else(|| SchemaError::new("Dependencies are not an array").in_node("dependencies"))?;
+        for (i, dep) in deps.iter().enumerate() {
+            let dep_str = dep.as_str()
+                .ok_or_else(|| SchemaError::new("Dependency is not a string").in_node(i).in_node("dependencies"))?;
+            // Dependencies are looked up next to the schema that lists them, then in the schema root.
+            let mut dep_path = file_path_parent.join(format!("{}.quops", dep_str));
+            if !dep_path.exists() {
+                let root_path = schema_root().join(format!("{}.quops", dep_str));
+                if root_path.exists() {
+                    dep_path = root_path;
+                }
+            }
+            let dep_key = dep_path.canonicalize().unwrap_or(dep_path.clone());
+            // A dependency that is still being parsed is a recursive reference. Only its type is needed to refer to it.
+            let dep_schema = match loading.stack.iter().find(|(path, _)| *path == dep_key) {
+                Some((_, ty)) if ty == "union" => Schema::Union(UnionSchema { variants: Vec::new() }),
+                Some(_) => Schema::Record(RecordSchema { fields: Vec::new(), dependencies: HashMap::new(), recursive: true }),
+                None => Schema::parse_file(&dep_path, loading)
+                    .map_err(|e| e.in_node(i).in_node("dependencies"))?,
+            };
+            dependencies.insert(dep_str.to_string(), dep_schema);
+        }
+        Ok(dependencies)
+    }
+
+    /// Parses the schema file, returning the schema along with every schema file read, including dependencies.
+    pub fn parse_from_file(file_path: std::path::PathBuf) -> Result<(Self, Vec<std::path::PathBuf>), SchemaError> {
+        let mut loading = Loading::default();
+        let (source, schema_value) = loading.read_file(&file_path)?;
+        let mut parse = || {
+            let schema = Schema::parse_value(&schema_value, &file_path, &mut loading, None)?;
+            if let Schema::Record(record_schema) = &schema {
+                check_finite_size(&record_schema.fields, &mut Vec::new())?;
+            }
+            Ok(schema)
+        };
+        let schema = parse().map_err(|e: SchemaError| e.in_file(&file_path, &source))?;
+        Ok((schema, loading.files))
+    }
+
+    /// Parses the inline schema named `name` declared somewhere in the schema file, returning it along with
+    /// every schema file read.
+    pub fn parse_inline_from_file(file_path: std::path::PathBuf, name: &str) -> Result<(Self, Vec<std::path::PathBuf>), SchemaError> {
+        let mut loading = Loading::default();
+        let (source, schema_value) = loading.read_file(&file_path)?;
+        let (inline, inline_path) = find_inline(&schema_value, name)
+            .ok_or(format!("No inline type named '{}' found in '{}'", name, file_path.display()))?;
+
+        let mut parse = || {
+            let dependencies = Schema::value_dependencies(&schema_value, &file_path, &mut loading, None)?;
+            let schema = Schema::parse_value(inline, &file_path, &mut loading, Some(&dependencies))
+                .map_err(|e| inline_path.iter().rev().fold(e, |e, key| e.in_node(key)))?;
+            if let Schema::Record(record_schema) = &schema {
+                check_finite_size(&record_schema.fields, &mut Vec::new())
+                    .map_err(|e| inline_path.iter().rev().fold(e, |e, key| e.in_node(key)))?;
+            }
+            Ok(schema)
+        };
+        let schema = parse().map_err(|e: SchemaError| e.in_file(&file_path, &source))?;
+        Ok((schema, loading.files))
+    }
+
+    /// Reads a schema file, returning both its contents and the parsed JSON.
+    fn read_file(file_path: &std::path::Path) -> Result<(String, serde_json::Value), SchemaError> {
+        let schema_contents = std::fs::read_to_string(file_path)
+            .map_err(|e| format!("Failed to read schema file '{}': {}", file_path.display(), e))?;
         let schema_value = serde_json::from_str::<serde_json::Value>(&schema_contents)
-            .expect("Failed to parse schema file as JSON");
+            .map_err(|e| SchemaError::from_json(file_path, e))?;
+        Ok((schema_contents, schema_value))
+    }
+
+    fn parse_file(file_path: &std::path::Path, loading: &mut Loading) -> Result<Self, SchemaError> {
+        let (source, schema_value) = loading.read_file(file_path)?;
+        Schema::parse_value(&schema_value, file_path, loading, None)
+            .map_err(|e| e.in_file(file_path, &source))
+    }
 
+    /// Returns the dependencies of a schema. Inline schemas inherit the dependencies of the schema they are declared in.
+    fn value_dependencies(schema_value: &serde_json::Value, file_path: &std::path::Path, loading: &mut Loading, inherited: Option<&HashMap<String, Schema>>) -> Result<HashMap<String, Schema>, SchemaError> {
+        if let Some(inherited) = inherited {
+            return Ok(inherited.clone());
+        }
+        let ty = schema_value.get("type").and_then(|v| v.as_str()).unwrap_or_default();
+        let file_key = file_path.canonicalize().unwrap_or(file_path.to_path_buf());
+        loading.stack.push((file_key, ty.to_string()));
+        let dependencies = Schema::parse_dependencies(schema_value, file_path, loading);
+        loading.stack.pop();
+        dependencies
+    }
+
+    fn parse_value(schema_value: &serde_json::Value, file_path: &std::path::Path, loading: &mut Loading, inherited: Option<&HashMap<String, Schema>>) -> Result<Self, SchemaError> {
         let ty = schema_value.get("type").and_then(|v| v.as_str())
-            .expect("Schema type is not a string");
+            .ok_or_else(|| SchemaError::new("Schema type is not a string").in_node("type"))?;
 
         match ty {
             "record" => {
-                let file_path_parent = file_path.parent().unwrap_or(std::path::Path::new("../../../../../.."));
-                let dependencies = schema_value.get("dependencies")
-                    .and_then(|v| {
-                        let deps = v.as_array().expect("Dependencies are not an array");
-                        Some(deps.iter().map(|dep| {
-                            let dep_str = dep.as_str().expect("Dependency is not a string");
-                            let dep_path = file_path_parent.join(format!("{}.quops", dep_str));
-                            let dep_schema = Schema::parse_from_file(dep_path)
-                                .expect(&format!("Failed to parse dependency schema: {}", dep_str));
-                            (dep_str.to_string(), dep_schema)
-                        }).collect::<HashMap<_, _>>())
-                    })
-                    .unwrap_or(HashMap::new());
-
                 let mut record_schema = RecordSchema {
                     fields: Vec::new(),
-                    dependencies
+                    dependencies: Schema::value_dependencies(schema_value, file_path, loading, inherited)?,
+                    recursive: false,
                 };
 
-                for (name, field_value) in schema_value.get("fields").and_then(|v| v.as_object()).expect("Fields are not an object") {
-                    let field = record_schema.parse_field(name, field_value);
-                    match field {
-                        Ok(f) => record_schema.fields.push(f),
-                        Err(e) => return Err(format!("Failed to parse field '{}': {}", name, e)),
-                    }
+                let fields = schema_value.get("fields").and_then(|v| v.as_object())
+                    .ok_or_else(|| SchemaError::new("Fields are not an object").in_node("fields"))?;
+                for (name, field_value) in ordered_fields(fields).map_err(|e| e.in_node("fields"))? {
+                    let field = record_schema.parse_field(name, field_value)
+                        .map_err(|e| e.in_node(name).in_node("fields"))?;
+                    record_schema.fields.push(field);
                 }
 
                 Ok(Schema::Record(record_schema))
             }
             "enum" => {
-                let variants = schema_value.get("variants")
-                    .and_then(|v| v.as_array())
-                    .expect("Variants are not an array")
-                    .iter()
-                    .map(|v| v.as_str().expect("Variant is not a string").to_string())
-                    .collect::<Vec<_>>();
-
-                Ok(Schema::Enum(EnumSchema { variants }))
+                let variant_values = schema_value.get("variants").and_then(|v| v.as_array())
+                    .ok_or_else(|| SchemaError::new("Variants are not an array").in_node("variants"))?;
+                // Like Rust discriminants, a variant without an explicit id takes the id after the previous one.
+                let mut variants: Vec<EnumVariant> = Vec::new();
+                let mut next_id = Some(0u64);
+                for (i, variant_value) in variant_values.iter().enumerate() {
+                    let in_variant = |e: SchemaError| e.in_node(i).in_node("variants");
+                    let (name, id) = if let Some(name) = variant_value.as_str() {
+                        (name, next_id)
+                    } else {
+                        let name = variant_value.get("name").and_then(|v| v.as_str())
+                            .ok_or_else(|| in_variant(SchemaError::new("Variant 'name' is not a string").in_node("name")))?;
+                        let id = variant_value.get("id").map_or(Ok(next_id), |v| {
+                            v.as_u64().map(Some)
+                                .ok_or_else(|| in_variant(SchemaError::new(format!("Variant '{}' id is not an unsigned integer", name)).in_node("id")))
+                        })?;
+                        (name, id)
+                    };
+                    let id = id.ok_or_else(|| in_variant(SchemaError::new(format!("Variant '{}' id overflows u64", name))))?;
+                    if let Some(other) = variants.iter().find(|v| v.id == id) {
+                        return Err(in_variant(SchemaError::new(format!("Variants '{}' and '{}' have the same id {}", other.name, name, id))));
+                    }
+                    variants.push(EnumVariant { name: name.to_string(), id });
+                    next_id = id.checked_add(1);
+                }
+
+                let unknown = match schema_value.get("unknown") {
+                    Some(v) => Some(v.as_str().ok_or_else(|| SchemaError::new("Unknown variant is not a string").in_node("unknown"))?.to_string()),
+                    None => None,
+                };
+                if let Some(unknown) = unknown.as_ref().filter(|u| variants.iter().any(|v| v.name == **u)) {
+                    return Err(SchemaError::new(format!("Unknown variant '{}' cannot also be listed in variants", unknown)).in_node("unknown"));
+                }
+
+                let bits = match schema_value.get("bits") {
+                    Some(v) => {
+                        let bits = v.as_u64().filter(|&b| b <= 64)
+                            .ok_or_else(|| SchemaError::new("Enum 'bits' must be an integer between 0 and 64").in_node("bits"))? as u8;
+                        let max_id = variants.iter().map(|v| v.id).max().unwrap_or(0);
+                        if bits < 64 && max_id >> bits != 0 {
+                            return Err(SchemaError::new(format!("Enum 'bits' is {}, but id {} needs {} bits", bits, max_id, 64 - max_id.leading_zeros())).in_node("bits"));
+                        }
+                        Some(bits)
+                    },
+                    None => None,
+                };
+
+                // Without a fixed width, a newer schema with larger ids would read and write wider ids than older ones expect.
+                if let Some(unknown) = unknown.as_ref().filter(|_| bits.is_none()) {
+                    return Err(SchemaError::new(format!("Enum with unknown variant '{}' must set 'bits'", unknown)).in_node("unknown"));
+                }
+
+                Ok(Schema::Enum(EnumSchema { variants, unknown, bits }))
+            }
+            "union" => {
+                // Payloads are parsed like record fields, so they may refer to the same dependencies.
+                let payload_schema = RecordSchema {
+                    fields: Vec::new(),
+                    dependencies: Schema::value_dependencies(schema_value, file_path, loading, inherited)?,
+                    recursive: false,
+                };
+                let variant_values = schema_value.get("variants").and_then(|v| v.as_array())
+                    .ok_or_else(|| SchemaError::new("Variants are not an array").in_node("variants"))?;
+                let mut variants = Vec::new();
+                for (i, variant_value) in variant_values.iter().enumerate() {
+                    let variant = payload_schema.parse_union_variant(variant_value)
+                        .map_err(|e| e.in_node(i).in_node("variants"))?;
+                    variants.push(variant);
+                }
+                Ok(Schema::Union(UnionSchema { variants }))
             }
             _ => {
-                Err(format!("Unsupported schema type: {}", ty))
+                Err(SchemaError::new(format!("Unsupported schema type: {}", ty)).in_node("type"))
             }
         }
     }
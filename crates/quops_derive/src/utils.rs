@@ -1,8 +1,9 @@
 use std::fmt::{Debug, Display, Formatter};
-use std::ops::RangeInclusive;
-use darling::FromDeriveInput;
+use std::ops::{Bound, RangeBounds};
+use darling::{FromDeriveInput, FromVariant};
 use quote::ToTokens;
 use syn::Type;
+use crate::errors::SchemaError;
 use crate::field::{Field, FieldTrait};
 use crate::schema;
 use crate::schema::Schema;
@@ -11,6 +12,16 @@ use crate::schema::Schema;
 #[darling(attributes(schema))]
 pub struct SchemaAttr {
     pub path: String,
+    /// Selects an inline type declared within the schema file instead of the file's own type.
+    #[darling(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, FromVariant)]
+#[darling(attributes(quops))]
+pub struct VariantAttr {
+    #[darling(default)]
+    pub unknown: bool,
 }
 
 #[derive(Debug)]
@@ -53,8 +64,51 @@ pub fn camel_to_snake_case(s: &str) -> String {
     result
 }
 
-pub fn valid_types_for_range(range: &RangeInclusive<i128>, field_name: &str) -> Result<Vec<&'static str>, String> {
-    let (&min, &max) = (range.start(), range.end());
+/// Every integer type a field can be decoded into, with its range.
+const INT_TYPES: [(&str, i128, i128); 10] = [
+    ("u8", u8::MIN as i128, u8::MAX as i128),
+    ("u16", u16::MIN as i128, u16::MAX as i128),
+    ("u32", u32::MIN as i128, u32::MAX as i128),
+    ("u64", u64::MIN as i128, u64::MAX as i128),
+    ("u128", u128::MIN as i128, i128::MAX),
+    ("i8", i8::MIN as i128, i8::MAX as i128),
+    ("i16", i16::MIN as i128, i16::MAX as i128),
+    ("i32", i32::MIN as i128, i32::MAX as i128),
+    ("i64", i64::MIN as i128, i64::MAX as i128),
+    ("i128", i128::MIN, i128::MAX),
+];
+
+pub fn describe_range(range: &impl RangeBounds<i128>) -> String {
+    let start = match range.start_bound() {
+        Bound::Included(min) => min.to_string(),
+        _ => String::new(),
+    };
+    let end = match range.end_bound() {
+        Bound::Included(max) => format!("={}", max),
+        _ => String::new(),
+    };
+    format!("{}..{}", start, end)
+}
+
+pub fn valid_types_for_range(range: &impl RangeBounds<i128>, field_name: &str) -> Result<Vec<&'static str>, String> {
+    let (min, max) = match (range.start_bound(), range.end_bound()) {
+        (Bound::Included(&min), Bound::Included(&max)) => (min, max),
+        // A half-open range only fixes one end, so any type holding that bound and extending past it will do.
+        // Values beyond what the chosen type can hold are rejected when decoding.
+        (Bound::Included(&min), Bound::Unbounded) => {
+            return Ok(INT_TYPES.iter()
+                .filter(|&&(_, type_min, type_max)| type_min <= min && min < type_max)
+                .map(|&(ty, _, _)| ty)
+                .collect());
+        },
+        (Bound::Unbounded, Bound::Included(&max)) => {
+            return Ok(INT_TYPES.iter()
+                .filter(|&&(_, type_min, type_max)| type_min < max && max <= type_max)
+                .map(|&(ty, _, _)| ty)
+                .collect());
+        },
+        _ => return Err(format!("Field '{}' has an unsupported range", field_name)),
+    };
 
     if min >= 0 && max <= u8::MAX as i128 {
         Ok(vec!["u8", "u16", "u32", "u64", "u128", "i16", "i32", "i64", "i128"])
@@ -82,6 +136,10 @@ impl<'a> TypeHelper<'a> {
         TypeHelper { ty }
     }
 
+    pub fn ty(&self) -> &'a Type {
+        self.ty
+    }
+
     pub fn get_type(&self) -> Option<String> {
         if let Type::Path(type_path) = &self.ty {
             let segments = &type_path.path.segments;
@@ -92,7 +150,19 @@ impl<'a> TypeHelper<'a> {
         None
     }
 
+    /// The length of a Rust array type such as `[u8; 32]`.
+    pub fn array_length(&self) -> Option<u32> {
+        if let Type::Array(type_array) = &self.ty
+            && let syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(length), .. }) = &type_array.len {
+            return length.base10_parse().ok();
+        }
+        None
+    }
+
     pub fn inner_type(&self) -> Option<TypeHelper<'a>> {
+        if let Type::Array(type_array) = &self.ty {
+            return Some(TypeHelper::new(&type_array.elem));
+        }
         if let Type::Path(type_path) = &self.ty {
             let segment = &type_path.path.segments[0];
             if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
@@ -107,44 +177,50 @@ impl<'a> TypeHelper<'a> {
         None
     }
 
-    pub fn full_type(&self) -> String {
+    /// All type arguments of a generic type, e.g. the key and value types of `HashMap<K, V>`.
+    pub fn inner_types(&self) -> Vec<TypeHelper<'a>> {
         if let Type::Path(type_path) = &self.ty {
             let segment = &type_path.path.segments[0];
             if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
-                if let Some(inner_type) = args.args.first().and_then(|a| match a {
-                    syn::GenericArgument::Type(t) => Some(t),
+                return args.args.iter().filter_map(|a| match a {
+                    syn::GenericArgument::Type(t) => Some(TypeHelper::new(t)),
                     _ => None,
-                }) {
-                    let inner_type_str = TypeHelper::new(inner_type).get_type().unwrap_or_default();
-                    return format!("{}<{}>", segment.ident, inner_type_str);
-                }
+                }).collect();
+            }
+        }
+        Vec::new()
+    }
+
+    pub fn full_type(&self) -> String {
+        if let Type::Path(type_path) = &self.ty {
+            let segment = &type_path.path.segments[0];
+            let inner_types = self.inner_types();
+            if !inner_types.is_empty() {
+                let inner_type_strs = inner_types.iter().map(|t| t.full_type()).collect::<Vec<_>>();
+                return format!("{}<{}>", segment.ident, inner_type_strs.join(", "));
             }
             segment.ident.to_string()
+        } else if let Type::Array(type_array) = &self.ty {
+            let elem_type_str = TypeHelper::new(&type_array.elem).full_type();
+            format!("[{}; {}]", elem_type_str, type_array.len.to_token_stream())
         } else {
             self.ty.to_token_stream().to_string()
         }
     }
 }
 
-pub fn has_bytes_field(fields: &Vec<Field>) -> bool {
-    fields.iter().any(|f| {
-        match f {
-            Field::Bytes(_) => true,
-            Field::Array(array_field) => has_bytes_field(&vec![*array_field.items_field.clone()]),
-            Field::Record(record_field) => has_bytes_field(&record_field.fields),
-            _ => false,
-        }
-    })
-}
-
-pub fn validate_field_type(field: &Field, type_helper: &TypeHelper) -> Result<(), String> {
+pub fn validate_field_type(field: &Field, type_helper: &TypeHelper) -> Result<(), syn::Error> {
     let full_type = type_helper.full_type();
+    let error = |message: String| syn::Error::new_spanned(type_helper.ty(), message);
     match field {
         Field::Int(int_field) => {
-            let min = int_field.min.unwrap_or(i32::MIN) as i128;
-            let max = int_field.max.unwrap_or(i32::MAX) as i128;
-            let range = min..=max;
-            let mut valid_types = valid_types_for_range(&range, field.name())?
+            let range = match (int_field.min, int_field.max) {
+                (Some(min), Some(max)) => (Bound::Included(min), Bound::Included(max)),
+                (Some(min), None) => (Bound::Included(min), Bound::Unbounded),
+                (None, Some(max)) => (Bound::Unbounded, Bound::Included(max)),
+                (None, None) => (Bound::Included(i32::MIN as i128), Bound::Included(i32::MAX as i128)),
+            };
+            let mut valid_types = valid_types_for_range(&range, field.name()).map_err(error)?
                 .iter()
                 .map(|s| s.to_string())
                 .collect::<Vec<_>>();
@@ -156,80 +232,257 @@ pub fn validate_field_type(field: &Field, type_helper: &TypeHelper) -> Result<()
             }
 
             if !valid_types.contains(&full_type) {
-                return Err(format!("Field '{}' has range {:?}{}. Valid types are: {:?}", field.name(), range, if field.nullable() { " and is nullable" } else { "" }, valid_types))
+                let nullable = if field.nullable() { " and is nullable" } else { "" };
+                return Err(error(format!("Field '{}' has range {}{}, which type '{}' cannot hold\nhelp: use one of {}", field.name(), describe_range(&range), nullable, full_type, valid_types.join(", "))))
             }
         },
         Field::Boolean(_) => {
             if type_helper.get_type() != Some("bool".to_string()) && !type_helper.get_type().map_or(false, |t| t.starts_with("Option<")) {
-                return Err(format!("Field '{}' is a boolean but has type '{}'", field.name(), full_type))
+                return Err(error(format!("Field '{}' is a boolean but has type '{}'", field.name(), full_type)))
+            }
+        },
+        Field::Float(float_field) => {
+            let ty = format!("f{}", float_field.width);
+            let expected_type = if field.nullable() { format!("Option<{}>", ty) } else { ty };
+            if full_type != expected_type {
+                return Err(error(format!("Field '{}' should be of type '{}' but has type '{}'", field.name(), expected_type, full_type)))
+            }
+        },
+        Field::QuantizedFloat(_) => {
+            let valid_types = if field.nullable() { ["Option<f32>", "Option<f64>"] } else { ["f32", "f64"] };
+            if !valid_types.contains(&full_type.as_str()) {
+                return Err(error(format!("Field '{}' is a quantized float but has type '{}'. Valid types are: {}", field.name(), full_type, valid_types.join(", "))))
             }
         },
-        Field::Bytes(_) => {
-            let expected_type = if field.nullable() { "Option<Vec<u8>>" } else { "Vec<u8>" };
+        Field::Bytes(bytes_field) => {
+            let value_type = match bytes_field.length {
+                Some(length) => format!("[u8; {}]", length),
+                None => "Vec<u8>".to_string(),
+            };
+            let expected_type = if field.nullable() { format!("Option<{}>", value_type) } else { value_type };
             if full_type != expected_type {
-                return Err(format!("Field '{}' should be of type '{}' but has type '{}'", field.name(), expected_type, full_type))
+                return Err(error(format!("Field '{}' should be of type '{}' but has type '{}'", field.name(), expected_type, full_type)))
+            }
+        }
+        Field::String(_) => {
+            let expected_type = if field.nullable() { "Option<String>" } else { "String" };
+            if full_type != expected_type {
+                return Err(error(format!("Field '{}' should be of type '{}' but has type '{}'", field.name(), expected_type, full_type)))
             }
         }
         Field::Array(array_field) => {
-            let inner_type_helper = type_helper.inner_type().ok_or(format!("Field '{}' is an array but does not have an inner type", field.name()))?;
+            let array_type_helper = if field.nullable() {
+                type_helper.inner_type().ok_or_else(|| error(format!("Field '{}' is nullable but has type '{}'", field.name(), full_type)))?
+            } else {
+                TypeHelper::new(type_helper.ty())
+            };
+            if let Some(length) = array_field.length
+                && array_type_helper.array_length() != Some(length) {
+                return Err(error(format!("Field '{}' is an array of length {} but has type '{}'. Expected a Rust array such as '[T; {}]'", field.name(), length, full_type, length)))
+            }
+            let inner_type_helper = array_type_helper.inner_type().ok_or_else(|| error(format!("Field '{}' is an array but does not have an inner type", field.name())))?;
             return validate_field_type(&array_field.items_field, &inner_type_helper);
         },
+        Field::Default(default_field) => {
+            return validate_field_type(&default_field.field, type_helper);
+        },
+        Field::Const(_) => {
+            // The value lives in the schema, so the Rust type can only be a unit or marker type built with `Default`.
+            let is_data_type = INT_TYPES.iter().any(|(ty, _, _)| *ty == full_type)
+                || ["bool", "f32", "f64", "String", "Vec", "Option"].contains(&type_helper.get_type().unwrap_or_default().as_str());
+            if is_data_type {
+                return Err(error(format!("Field '{}' is a const but has type '{}'. Use '()' or a unit marker type, or leave the field out", field.name(), full_type)))
+            }
+        },
+        Field::Map(map_field) => {
+            let map_type_helper = if field.nullable() {
+                type_helper.inner_type().ok_or_else(|| error(format!("Field '{}' is nullable but has type '{}'", field.name(), full_type)))?
+            } else {
+                TypeHelper::new(type_helper.ty())
+            };
+            let map_type = map_type_helper.get_type();
+            let inner_types = map_type_helper.inner_types();
+            if !matches!(map_type.as_deref(), Some("HashMap" | "BTreeMap")) || inner_types.len() != 2 {
+                return Err(error(format!("Field '{}' is a map but has type '{}'. Expected 'HashMap<K, V>' or 'BTreeMap<K, V>'", field.name(), full_type)))
+            }
+            validate_field_type(&map_field.keys_field, &inner_types[0])?;
+            return validate_field_type(&map_field.values_field, &inner_types[1]);
+        },
         _ => {}
     }
     Ok(())
 }
 
-pub fn validate_record_schema(schema: &schema::RecordSchema, data_struct: &syn::DataStruct) -> Result<(), String> {
+/// Collects validation errors, so that all of them are reported at once.
+#[derive(Default)]
+struct Errors(Option<syn::Error>);
+
+impl Errors {
+    fn push(&mut self, error: syn::Error) {
+        match &mut self.0 {
+            Some(errors) => errors.combine(error),
+            None => self.0 = Some(error),
+        }
+    }
+
+    fn finish(self) -> Result<(), syn::Error> {
+        self.0.map_or(Ok(()), Err)
+    }
+}
+
+pub fn validate_record_schema(schema: &schema::RecordSchema, data_struct: &syn::DataStruct) -> Result<(), syn::Error> {
+    let mut errors = Errors::default();
     let struct_fields = data_struct.fields.iter().map(|field| {
-        let field_name = field.ident.clone().unwrap().to_string();
-        let field_name_json = snake_to_camel_case(&field_name);
-        let type_helper = TypeHelper::new(&field.ty);
-        (field_name, field_name_json, type_helper)
+        let field_name_json = snake_to_camel_case(&field.ident.clone().unwrap().to_string());
+        (field, field_name_json)
     }).collect::<Vec<_>>();
 
     for field in &schema.fields {
         let field_name = field.name();
-        if !struct_fields.iter().any(|(_, field_name_json, _)| field_name_json == &field_name) {
-            return Err(format!("Field '{}' is not present in struct", field_name));
+        // Const fields carry no data, so the struct may leave them out.
+        if matches!(field, Field::Const(_)) {
+            continue;
+        }
+        if !struct_fields.iter().any(|(_, field_name_json)| field_name_json == &field_name) {
+            errors.push(syn::Error::new_spanned(&data_struct.fields, format!("Field '{}' is not present in struct", field_name)));
         }
     }
 
-    for (field_name, field_name_json, type_helper) in struct_fields {
-        let field = match schema.fields.iter().find(|f| f.name() == field_name_json) {
-            Some(f) => f,
-            None => return Err(format!("Field '{}' not found in schema", field_name_json)),
-        };
+    for (struct_field, field_name_json) in struct_fields {
+        match schema.fields.iter().find(|f| f.name() == field_name_json) {
+            Some(field) => {
+                if let Err(err) = validate_field_type(field, &TypeHelper::new(&struct_field.ty)) {
+                    errors.push(err);
+                }
+            },
+            None => errors.push(syn::Error::new_spanned(&struct_field.ident, format!("Field '{}' not found in schema", field_name_json))),
+        }
+    }
+
+    errors.finish()
+}
 
-        if let Err(err) = validate_field_type(field, &type_helper) {
-            return Err(format!("Field '{}' has invalid type: {}", field_name, err));
+/// Finds the catch-all variant, named by the schema's `unknown` setting or marked with `#[quops(unknown)]`.
+///
+/// The variant holds the raw id and writes it back unchanged, so `Unknown(id)` holding the id of a
+/// known variant encodes exactly like that variant and decodes as it.
+pub fn unknown_variant<'a>(schema: &schema::EnumSchema, data_enum: &'a syn::DataEnum) -> Result<Option<&'a syn::Variant>, syn::Error> {
+    let mut marked = Vec::new();
+    for variant in &data_enum.variants {
+        let attr = VariantAttr::from_variant(variant)?;
+        if attr.unknown {
+            marked.push(variant);
         }
     }
 
-    Ok(())
+    let variant = match (marked.as_slice(), &schema.unknown) {
+        ([], None) => return Ok(None),
+        ([variant], None) => *variant,
+        ([], Some(name)) | ([_], Some(name)) => {
+            let variant = data_enum.variants.iter()
+                .find(|v| v.ident == name)
+                .ok_or_else(|| syn::Error::new_spanned(&data_enum.variants, format!("Unknown variant '{}' is not present in enum", name)))?;
+            if let Some(marked) = marked.first().filter(|m| m.ident != variant.ident) {
+                return Err(syn::Error::new_spanned(&marked.ident, format!("Variant '{}' is marked as unknown, but the schema names '{}'", marked.ident, name)));
+            }
+            variant
+        },
+        _ => return Err(syn::Error::new_spanned(&marked[1].ident, "Only one variant can be marked as unknown")),
+    };
+
+    if !schema.has_fixed_bits() {
+        return Err(syn::Error::new_spanned(&variant.ident, format!("Unknown variant '{}' requires the schema to set 'bits'", variant.ident)));
+    }
+
+    let is_raw_value = match &variant.fields {
+        syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+            TypeHelper::new(&fields.unnamed[0].ty).full_type() == "u64"
+        },
+        _ => false,
+    };
+    if !is_raw_value {
+        return Err(syn::Error::new_spanned(variant, format!("Unknown variant '{}' must hold the raw value, as in '{}(u64)'", variant.ident, variant.ident)));
+    }
+
+    Ok(Some(variant))
 }
 
-pub fn validate_enum_schema(schema: &schema::EnumSchema, data_enum: &syn::DataEnum) -> Result<(), String> {
+pub fn validate_enum_schema(schema: &schema::EnumSchema, data_enum: &syn::DataEnum) -> Result<(), syn::Error> {
+    let mut errors = Errors::default();
+    let unknown = unknown_variant(schema, data_enum)?;
+
     for variant in &data_enum.variants {
-        let variant_name = variant.ident.to_string();
-        if !schema.variants.iter().any(|v| *v == variant_name) {
-            return Err(format!("Variant '{}' is not present in schema", variant_name));
+        if unknown.is_some_and(|u| u.ident == variant.ident) {
+            continue;
+        }
+        if !schema.variants.iter().any(|v| variant.ident == v.name) {
+            errors.push(syn::Error::new_spanned(&variant.ident, format!("Variant '{}' is not present in schema", variant.ident)));
         }
     }
 
     for variant in &schema.variants {
-        if !data_enum.variants.iter().any(|v| v.ident.to_string() == *variant) {
-            return Err(format!("Variant '{}' is not present in enum", variant));
+        if !data_enum.variants.iter().any(|v| v.ident == variant.name) {
+            errors.push(syn::Error::new_spanned(&data_enum.variants, format!("Variant '{}' is not present in enum", variant.name)));
         }
     }
 
-    Ok(())
+    errors.finish()
+}
+
+pub fn validate_union_schema(schema: &schema::UnionSchema, data_enum: &syn::DataEnum) -> Result<(), syn::Error> {
+    let mut errors = Errors::default();
+    for variant in &data_enum.variants {
+        if !schema.variants.iter().any(|v| variant.ident == v.name) {
+            errors.push(syn::Error::new_spanned(&variant.ident, format!("Variant '{}' is not present in schema", variant.ident)));
+        }
+    }
+
+    for variant in &schema.variants {
+        let Some(enum_variant) = data_enum.variants.iter().find(|v| v.ident == variant.name) else {
+            errors.push(syn::Error::new_spanned(&data_enum.variants, format!("Variant '{}' is not present in enum", variant.name)));
+            continue;
+        };
+
+        match (&variant.payload, &enum_variant.fields) {
+            (schema::UnionPayload::None, syn::Fields::Unit) => {},
+            (schema::UnionPayload::Field(field), syn::Fields::Unnamed(fields)) if fields.unnamed.len() == 1 => {
+                if let Err(err) = validate_field_type(field, &TypeHelper::new(&fields.unnamed[0].ty)) {
+                    errors.push(err);
+                }
+            },
+            (schema::UnionPayload::Fields(fields), syn::Fields::Named(enum_fields)) => {
+                for field in fields {
+                    let field_name = camel_to_snake_case(field.name());
+                    match enum_fields.named.iter().find(|f| f.ident.as_ref().is_some_and(|ident| *ident == field_name)) {
+                        Some(enum_field) => {
+                            if let Err(err) = validate_field_type(field, &TypeHelper::new(&enum_field.ty)) {
+                                errors.push(err);
+                            }
+                        },
+                        None => errors.push(syn::Error::new_spanned(enum_fields, format!("Field '{}' of variant '{}' is not present in enum", field_name, variant.name))),
+                    }
+                }
+                for enum_field in &enum_fields.named {
+                    let field_name_json = snake_to_camel_case(&enum_field.ident.as_ref().unwrap().to_string());
+                    if !fields.iter().any(|f| f.name() == field_name_json) {
+                        errors.push(syn::Error::new_spanned(&enum_field.ident, format!("Field '{}' of variant '{}' not found in schema", field_name_json, variant.name)));
+                    }
+                }
+            },
+            (schema::UnionPayload::None, _) => errors.push(syn::Error::new_spanned(enum_variant, format!("Variant '{}' has no payload in schema, so it must be a unit variant", variant.name))),
+            (schema::UnionPayload::Field(_), _) => errors.push(syn::Error::new_spanned(enum_variant, format!("Variant '{}' has a payload in schema, so it must be a tuple variant with one field", variant.name))),
+            (schema::UnionPayload::Fields(_), _) => errors.push(syn::Error::new_spanned(enum_variant, format!("Variant '{}' has fields in schema, so it must be a struct variant", variant.name))),
+        }
+    }
+
+    errors.finish()
 }
 
 #[derive(Debug)]
 pub enum SchemaParseError {
     NoAttribute(String),
     FileNotFound(String),
-    ParseError(String),
+    ParseError(SchemaError),
 }
 
 impl Display for SchemaParseError {
@@ -244,7 +497,8 @@ impl Display for SchemaParseError {
 
 impl std::error::Error for SchemaParseError {}
 
-pub fn parse_schema(input: &syn::DeriveInput) -> Result<Schema, SchemaParseError> {
+/// Parses the schema named by the `#[schema(...)]` attribute, returning it along with every schema file read.
+pub fn parse_schema(input: &syn::DeriveInput) -> Result<(Schema, Vec<std::path::PathBuf>), SchemaParseError> {
     let schema_attr = match SchemaAttr::from_derive_input(input) {
         Ok(attr) => attr,
         Err(err) => {
@@ -253,13 +507,29 @@ pub fn parse_schema(input: &syn::DeriveInput) -> Result<Schema, SchemaParseError
         }
     };
 
-    let path = std::path::Path::new(&schema_attr.path);
+    let path = schema::resolve_schema_path(&schema_attr.path);
 
     if !path.exists() {
-        let path = path.to_str().unwrap();
-        return Err(SchemaParseError::FileNotFound(path.to_string()));
+        return Err(SchemaParseError::FileNotFound(path.display().to_string()));
     }
 
-    Schema::parse_from_file(path.to_path_buf())
-        .map_err(|err| SchemaParseError::ParseError(err.to_string()))
-}
\ No newline at end of file
+    let schema = match &schema_attr.name {
+        Some(name) => Schema::parse_inline_from_file(path, name),
+        None => Schema::parse_from_file(path),
+    };
+    schema.map_err(SchemaParseError::ParseError)
+}
+
+/// Refers to every schema file read with `include_bytes!`, so that cargo rebuilds the crate when one of them changes.
+/// The schema root setting is referred to as well, since it decides which files are read.
+pub fn track_schema_files(files: &[std::path::PathBuf]) -> proc_macro2::TokenStream {
+    let mut files = files.iter()
+        .map(|file| file.canonicalize().unwrap_or(file.clone()).display().to_string())
+        .collect::<Vec<_>>();
+    files.sort();
+    files.dedup();
+    quote::quote! {
+        const _: Option<&str> = option_env!("QUOPS_SCHEMA_ROOT");
+        #(const _: &[u8] = include_bytes!(#files);)*
+    }
+}
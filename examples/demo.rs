@@ -36,6 +36,7 @@ struct ScratchphraseRules {
 
 #[derive(Debug, quops::Encode, quops::Decode)]
 #[schema(path = "./schemas/Role.quops")]
+#[allow(dead_code)]
 enum Role {
     Player,
     Leader,
@@ -46,6 +47,7 @@ enum Role {
 
 #[derive(Debug, quops::Encode, quops::Decode)]
 #[schema(path = "./schemas/LastRoundWinner.quops")]
+#[allow(dead_code)]
 struct LastRoundWinner {
     id: i32,
     role: Role,
@@ -53,6 +55,7 @@ struct LastRoundWinner {
 
 #[derive(Debug, quops::Encode, quops::Decode)]
 #[schema(path = "./schemas/ScratchphraseGameStats.quops")]
+#[allow(dead_code)]
 struct ScratchphraseGameStats {
     total_time: i32,
     total_words_used: i32,
@@ -60,6 +63,7 @@ struct ScratchphraseGameStats {
 
 #[derive(Debug, quops::Encode, quops::Decode)]
 #[schema(path = "./schemas/ScratchphraseLastRound.quops")]
+#[allow(dead_code)]
 struct ScratchphraseLastRound {
     winner: Option<LastRoundWinner>,
     game_stats: ScratchphraseGameStats,
@@ -3,12 +3,14 @@ use std::fmt::{Debug, Display, Formatter};
 #[derive(Debug)]
 pub enum WriteError {
     ValueTooLarge(String),
+    BufferTooSmall(String),
 }
 
 impl Display for WriteError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             WriteError::ValueTooLarge(message) => write!(f, "Value too large: {}", message),
+            WriteError::BufferTooSmall(message) => write!(f, "Buffer too small: {}", message),
         }
     }
 }
@@ -18,6 +20,7 @@ impl std::error::Error for WriteError {}
 #[derive(Debug)]
 pub enum ReadError {
     NotEnoughBits(String),
+    NotEnoughBytes(String),
     InvalidBitCount(String),
 }
 
@@ -25,6 +28,7 @@ impl Display for ReadError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             ReadError::NotEnoughBits(message) => write!(f, "Not enough bits: {}", message),
+            ReadError::NotEnoughBytes(message) => write!(f, "Not enough bytes: {}", message),
             ReadError::InvalidBitCount(message) => write!(f, "Invalid bit count: {}", message),
         }
     }
@@ -32,21 +36,234 @@ impl Display for ReadError {
 
 impl std::error::Error for ReadError {}
 
-pub struct BitWriter {
-    bytes: Vec<u8>,
+/// Stores `value` as 8 little-endian bytes at `ptr`.
+///
+/// On x86_64 this goes through SSE2 (part of the baseline target), everywhere else
+/// it falls back to a plain unaligned store. Both produce the same byte order.
+///
+/// # Safety
+///
+/// `ptr` must be valid for writes of 8 bytes.
+#[inline(always)]
+unsafe fn store_u64(ptr: *mut u8, value: u64) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let m128i = std::mem::transmute::<u128, std::arch::x86_64::__m128i>(value as u128);
+        std::arch::x86_64::_mm_storeu_si64(ptr, m128i);
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        std::ptr::write_unaligned(ptr as *mut u64, value.to_le());
+    }
+}
+
+/// Loads 8 little-endian bytes from `ptr`.
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of 8 bytes.
+#[inline(always)]
+unsafe fn load_u64(ptr: *const u8) -> u64 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let value = std::arch::x86_64::_mm_loadu_si64(ptr);
+        let value: [u64; 2] = std::mem::transmute(value);
+        value[0]
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        u64::from_le(std::ptr::read_unaligned(ptr as *const u64))
+    }
+}
+
+/// A byte buffer that a [`BitWriter`] flushes its bits into.
+///
+/// Implemented for an owned `Vec<u8>`, for `&mut Vec<u8>` (appending after its current contents)
+/// and for `&mut [u8]` (writing from its start without ever growing it).
+pub trait BitOutput {
+    /// Byte offset at which a new writer starts writing.
+    fn start(&self) -> usize;
+
+    /// The whole underlying buffer.
+    fn as_bytes(&self) -> &[u8];
+
+    /// Stores a full little-endian word at byte offset `pos`.
+    fn put_word(&mut self, pos: usize, word: u64) -> Result<(), WriteError>;
+
+    /// Stores the lowest `len` bytes of `word` at byte offset `pos`.
+    fn put_tail(&mut self, pos: usize, word: u64, len: usize) -> Result<(), WriteError>;
+
+    /// Copies `bytes` to byte offset `pos`.
+    fn put_bytes(&mut self, pos: usize, bytes: &[u8]) -> Result<(), WriteError>;
+
+    /// Discards everything from byte offset `pos` onwards.
+    fn truncate(&mut self, pos: usize);
+}
+
+impl BitOutput for Vec<u8> {
+    #[inline(always)]
+    fn start(&self) -> usize {
+        self.len()
+    }
+
+    #[inline(always)]
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+
+    #[inline(always)]
+    fn put_word(&mut self, pos: usize, word: u64) -> Result<(), WriteError> {
+        debug_assert_eq!(pos, self.len());
+        self.reserve_exact(8);
+
+        // The store goes into the spare capacity reserved above, and the length is only
+        // extended once those bytes are initialized.
+        unsafe {
+            store_u64(self.as_mut_ptr().add(pos), word);
+            self.set_len(pos + 8);
+        }
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn put_tail(&mut self, pos: usize, word: u64, len: usize) -> Result<(), WriteError> {
+        debug_assert_eq!(pos, self.len());
+
+        if self.capacity() - pos >= 8 {
+            // Fast path: there is room for a full word, so store all 8 bytes and only
+            // expose the ones that actually carry bits.
+            unsafe {
+                store_u64(self.as_mut_ptr().add(pos), word);
+                self.set_len(pos + len);
+            }
+        } else {
+            self.extend_from_slice(&word.to_le_bytes()[..len]);
+        }
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn put_bytes(&mut self, pos: usize, bytes: &[u8]) -> Result<(), WriteError> {
+        debug_assert_eq!(pos, self.len());
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn truncate(&mut self, pos: usize) {
+        Vec::truncate(self, pos);
+    }
+}
+
+impl BitOutput for &mut Vec<u8> {
+    #[inline(always)]
+    fn start(&self) -> usize {
+        (**self).start()
+    }
+
+    #[inline(always)]
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+
+    #[inline(always)]
+    fn put_word(&mut self, pos: usize, word: u64) -> Result<(), WriteError> {
+        (**self).put_word(pos, word)
+    }
+
+    #[inline(always)]
+    fn put_tail(&mut self, pos: usize, word: u64, len: usize) -> Result<(), WriteError> {
+        (**self).put_tail(pos, word, len)
+    }
+
+    #[inline(always)]
+    fn put_bytes(&mut self, pos: usize, bytes: &[u8]) -> Result<(), WriteError> {
+        (**self).put_bytes(pos, bytes)
+    }
+
+    #[inline(always)]
+    fn truncate(&mut self, pos: usize) {
+        (**self).truncate(pos);
+    }
+}
+
+impl BitOutput for &mut [u8] {
+    #[inline(always)]
+    fn start(&self) -> usize {
+        0
+    }
+
+    #[inline(always)]
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+
+    #[inline(always)]
+    fn put_word(&mut self, pos: usize, word: u64) -> Result<(), WriteError> {
+        let capacity = self.len();
+        match self.get_mut(pos..pos + 8) {
+            Some(dst) => {
+                unsafe { store_u64(dst.as_mut_ptr(), word); }
+                Ok(())
+            },
+            None => Err(buffer_too_small(capacity, pos + 8)),
+        }
+    }
+
+    #[inline(always)]
+    fn put_tail(&mut self, pos: usize, word: u64, len: usize) -> Result<(), WriteError> {
+        self.put_bytes(pos, &word.to_le_bytes()[..len])
+    }
+
+    #[inline(always)]
+    fn put_bytes(&mut self, pos: usize, bytes: &[u8]) -> Result<(), WriteError> {
+        let capacity = self.len();
+        match self.get_mut(pos..pos + bytes.len()) {
+            Some(dst) => {
+                dst.copy_from_slice(bytes);
+                Ok(())
+            },
+            None => Err(buffer_too_small(capacity, pos + bytes.len())),
+        }
+    }
+
+    #[inline(always)]
+    fn truncate(&mut self, _pos: usize) {}
+}
+
+#[cold]
+fn buffer_too_small(capacity: usize, required: usize) -> WriteError {
+    WriteError::BufferTooSmall(format!("Output buffer holds {} bytes, but at least {} are needed", capacity, required))
+}
+
+pub struct BitWriter<O: BitOutput = Vec<u8>> {
+    output: O,
+    start: usize,
     buffer: u64,
     buffer_filled: u8,
     bytes_written: usize,
+    /// Deferred byte buffers, copied back to back, and the length of each.
+    tail_bytes: Vec<u8>,
+    tail_lengths: Vec<usize>,
 }
 
-impl BitWriter {
+impl<O: BitOutput> BitWriter<O> {
+    /// Creates a writer over `output`. A `Vec` that already holds data is appended to.
     #[inline(always)]
-    pub fn with_capacity(capacity: usize) -> Self {
+    pub fn new(output: O) -> Self {
+        let start = output.start();
         BitWriter {
-            bytes: Vec::with_capacity(capacity),
+            output,
+            start,
             buffer: 0,
             buffer_filled: 0,
-            bytes_written: 0,
+            bytes_written: start,
+            tail_bytes: Vec::new(),
+            tail_lengths: Vec::new(),
         }
     }
 
@@ -59,6 +276,11 @@ impl BitWriter {
             )));
         }
 
+        // A zero-width write carries no data, and shifting by a full buffer width would overflow.
+        if count == 0 {
+            return Ok(());
+        }
+
         if self.buffer_filled + count > 64 {
             let available_space = 64 - self.buffer_filled;
             if available_space > 0 {
@@ -66,12 +288,7 @@ impl BitWriter {
                 self.buffer |= (value & mask) << self.buffer_filled;
             }
 
-            self.bytes.reserve_exact(8);
-
-            unsafe { self.bytes.set_len(self.bytes_written + 8); }
-            let ptr = unsafe { self.bytes.as_mut_ptr().add(self.bytes_written) };
-            let m128i = unsafe { std::mem::transmute::<u128, std::arch::x86_64::__m128i>(self.buffer as u128) };
-            unsafe { std::arch::x86_64::_mm_storeu_si64(ptr as *mut _, m128i); }
+            self.output.put_word(self.bytes_written, self.buffer)?;
             self.bytes_written += 8;
 
             self.buffer = value >> available_space;
@@ -84,20 +301,81 @@ impl BitWriter {
         Ok(())
     }
 
-    // TODO: `self.bytes` after calling `set_len` could be less than a multiple of 8 bytes needed for `_mm_storeu_si64`,
-    // TODO: resulting in writing to potentially uninitialized memory.
+    /// Appends raw bytes after the bits written so far, padding the last partial byte with zeros.
     #[inline(always)]
-    pub fn into_bytes(mut self) -> Vec<u8> {
-        let additional_bytes = ((self.buffer_filled + 7) / 8) as usize;
-        let total_bytes = self.bytes_written + additional_bytes;
-        self.bytes.reserve_exact(additional_bytes);
-        unsafe {
-            self.bytes.set_len(total_bytes);
-            let ptr = self.bytes.as_mut_ptr().add(self.bytes_written);
-            let m128i = std::mem::transmute::<u128, std::arch::x86_64::__m128i>(self.buffer as u128);
-            std::arch::x86_64::_mm_storeu_si64(ptr as *mut _, m128i);
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), WriteError> {
+        self.flush()?;
+        self.output.put_bytes(self.bytes_written, bytes)?;
+        self.bytes_written += bytes.len();
+        Ok(())
+    }
+
+    /// Queues a copy of a byte buffer to be written after all bits, once the writer is finished.
+    ///
+    /// Buffers are laid out in reverse order, so that [`BitReader::read_tail_bytes`] can take
+    /// them back from the end of the message in the order they were queued.
+    #[inline(always)]
+    pub fn defer_bytes(&mut self, bytes: &[u8]) {
+        self.tail_bytes.extend_from_slice(bytes);
+        self.tail_lengths.push(bytes.len());
+    }
+
+    /// Flushes the pending bits followed by the deferred byte buffers, and returns the number
+    /// of bytes written since the writer was created or last reset.
+    #[inline(always)]
+    pub fn finish(&mut self) -> Result<usize, WriteError> {
+        self.flush()?;
+        let mut end = self.tail_bytes.len();
+        while let Some(length) = self.tail_lengths.pop() {
+            self.output.put_bytes(self.bytes_written, &self.tail_bytes[end - length..end])?;
+            self.bytes_written += length;
+            end -= length;
         }
-        self.bytes
+        self.tail_bytes.clear();
+        Ok(self.bytes_written - self.start)
+    }
+
+    /// The bytes written since the writer was created or last reset.
+    #[inline(always)]
+    pub fn written(&self) -> &[u8] {
+        &self.output.as_bytes()[self.start..self.bytes_written]
+    }
+
+    /// Discards everything written so far, so the writer can be reused over the same buffer.
+    ///
+    /// The queue of deferred byte buffers keeps its capacity, so a reused writer does not allocate
+    /// for it again.
+    #[inline(always)]
+    pub fn reset(&mut self) {
+        self.output.truncate(self.start);
+        self.buffer = 0;
+        self.buffer_filled = 0;
+        self.bytes_written = self.start;
+        self.tail_bytes.clear();
+        self.tail_lengths.clear();
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> Result<(), WriteError> {
+        let additional_bytes = self.buffer_filled.div_ceil(8) as usize;
+        self.output.put_tail(self.bytes_written, self.buffer, additional_bytes)?;
+        self.bytes_written += additional_bytes;
+        self.buffer = 0;
+        self.buffer_filled = 0;
+        Ok(())
+    }
+}
+
+impl BitWriter<Vec<u8>> {
+    #[inline(always)]
+    pub fn with_capacity(capacity: usize) -> Self {
+        BitWriter::new(Vec::with_capacity(capacity))
+    }
+
+    #[inline(always)]
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        self.finish().expect("Flushing into a Vec cannot fail");
+        self.output
     }
 
     // #[inline(always)]
@@ -169,15 +447,16 @@ impl BitWriter {
     // }
 }
 
-impl Debug for BitWriter {
+impl<O: BitOutput> Debug for BitWriter<O> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let bytes = self.written();
         let mut binary_string = String::new();
-        for byte in self.bytes.as_slice().iter() {
+        for byte in bytes.iter() {
             binary_string.push_str(&format!("{:08b}, ", byte));
         }
         binary_string.pop();
         binary_string.pop();
-        write!(f, "BitWriter {{\n\tbytes: {:?},\n\tbits: [{}]\n}}", self.bytes.to_vec(), binary_string)
+        write!(f, "BitWriter {{\n\tbytes: {:?},\n\tbits: [{}]\n}}", bytes, binary_string)
     }
 }
 
@@ -188,6 +467,7 @@ pub struct BitReader<'a> {
     buffer: u128,
     filled: u8,
     byte_idx: usize,
+    tail_end: usize,
 }
 
 impl<'a> BitReader<'a> {
@@ -200,9 +480,34 @@ impl<'a> BitReader<'a> {
             buffer: 0,
             filled: 0,
             byte_idx: 0,
+            tail_end: bytes.len(),
         }
     }
 
+    /// The number of bits left in the bits section, excluding bytes already taken by `read_tail_bytes`.
+    #[inline(always)]
+    pub fn remaining_bits(&self) -> usize {
+        self.bits - self.bit_position
+    }
+
+    /// Takes the next `length` bytes from the end of the input, i.e. the counterpart of
+    /// [`BitWriter::defer_bytes`]. The bits section shrinks accordingly.
+    #[inline(always)]
+    pub fn read_tail_bytes(&mut self, length: usize) -> Result<&'a [u8], ReadError> {
+        let start = match self.tail_end.checked_sub(length) {
+            Some(start) if start * 8 >= self.bit_position => start,
+            _ => {
+                let available = self.tail_end - self.bit_position.div_ceil(8);
+                return Err(ReadError::NotEnoughBytes(format!("Requested {} bytes, but only {} bytes available", length, available)));
+            },
+        };
+
+        let bytes = &self.bytes[start..self.tail_end];
+        self.tail_end = start;
+        self.bits = start * 8;
+        Ok(bytes)
+    }
+
     // #[inline(always)]
     // pub fn read(&mut self, mut count: u8) -> Result<u64, ReadError> {
     //     if count > 64 {
@@ -276,23 +581,27 @@ impl<'a> BitReader<'a> {
 
     #[inline(always)]
     pub fn read(&mut self, count: u8) -> Result<u64, ReadError> {
-        // Every field in the schema has a bit count of less than or equal to 64.
-        // Therefore, we can safely assume that `count` is always less than or equal to 64.
+        if count > 64 {
+            return Err(ReadError::InvalidBitCount(format!("Requested {} bits, but maximum is 64", count)));
+        }
 
         let available_bits = self.bits - self.bit_position;
         if (count as usize) > available_bits {
             return Err(ReadError::NotEnoughBits(format!("Requested {} bits, but only {} bits available", count, available_bits)));
         }
 
+        if count == 0 {
+            return Ok(0);
+        }
+
         if self.filled < count {
-            unsafe {
-                let ptr = self.bytes.as_ptr().add(self.byte_idx);
-                let value = std::arch::x86_64::_mm_loadu_si64(ptr as *const _);
-                let value: [u64; 2] = std::mem::transmute(value);
-                self.buffer |= (value[0] as u128) << self.filled;
-                self.filled += 64;
-                self.byte_idx += 8;
-            }
+            let value = match self.bytes.get(self.byte_idx..self.byte_idx + 8) {
+                Some(word) => unsafe { load_u64(word.as_ptr()) },
+                None => self.load_tail(),
+            };
+            self.buffer |= (value as u128) << self.filled;
+            self.filled += 64;
+            self.byte_idx += 8;
         }
 
         let mask = (((1u64 << (count - 1)) - 1) << 1) + 1;
@@ -304,6 +613,17 @@ impl<'a> BitReader<'a> {
 
         Ok(value)
     }
+
+    /// Loads the last, partial word of the input, padding the missing high bytes with zeros.
+    ///
+    /// The padding is never handed out, since `read` checks the remaining bit count first.
+    #[cold]
+    fn load_tail(&self) -> u64 {
+        let tail = self.bytes.get(self.byte_idx..).unwrap_or_default();
+        let mut word = [0u8; 8];
+        word[..tail.len()].copy_from_slice(tail);
+        u64::from_le_bytes(word)
+    }
 }
 
 impl<'a> Debug for BitReader<'a> {
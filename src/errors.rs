@@ -7,6 +7,7 @@ use crate::bit::{ReadError, WriteError};
 pub enum EncodeError {
     OutOfBounds(String),
     NotSupported(String),
+    BufferTooSmall(String),
 }
 
 impl Display for EncodeError {
@@ -14,6 +15,7 @@ impl Display for EncodeError {
         match self {
             EncodeError::OutOfBounds(msg) => write!(f, "Encoding error: Out of bounds - {}", msg),
             EncodeError::NotSupported(msg) => write!(f, "Encoding error: Not supported - {}", msg),
+            EncodeError::BufferTooSmall(msg) => write!(f, "Encoding error: Buffer too small - {}", msg),
         }
     }
 }
@@ -24,6 +26,7 @@ impl From<WriteError> for EncodeError {
     fn from(error: WriteError) -> Self {
         match error {
             WriteError::ValueTooLarge(msg) => EncodeError::OutOfBounds(msg),
+            WriteError::BufferTooSmall(msg) => EncodeError::BufferTooSmall(msg),
         }
     }
 }
@@ -33,6 +36,8 @@ pub enum DecodeError {
     OutOfBounds(String),
     NotEnoughBytes(String),
     NotEnoughBits(String),
+    InvalidUtf8(String),
+    DuplicateKey(String),
 }
 
 impl Display for DecodeError {
@@ -41,6 +46,8 @@ impl Display for DecodeError {
             DecodeError::OutOfBounds(msg) => write!(f, "Decoding error: Out of bounds - {}", msg),
             DecodeError::NotEnoughBytes(msg) => write!(f, "Decoding error: Not enough bytes - {}", msg),
             DecodeError::NotEnoughBits(msg) => write!(f, "Decoding error: Not enough bits - {}", msg),
+            DecodeError::InvalidUtf8(msg) => write!(f, "Decoding error: Invalid UTF-8 - {}", msg),
+            DecodeError::DuplicateKey(msg) => write!(f, "Decoding error: Duplicate key - {}", msg),
         }
     }
 }
@@ -51,6 +58,7 @@ impl From<ReadError> for DecodeError {
     fn from(error: ReadError) -> Self {
         match error {
             ReadError::NotEnoughBits(msg) => DecodeError::NotEnoughBits(msg),
+            ReadError::NotEnoughBytes(msg) => DecodeError::NotEnoughBytes(msg),
             ReadError::InvalidBitCount(msg) => DecodeError::OutOfBounds(msg),
         }
     }
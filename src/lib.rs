@@ -1,15 +1,12 @@
 // TODO: Add documentation and examples
 // TODO: Add tests for encoding and decoding
-// TODO: Add support for unsigned, unbounded integers
-// TODO: Add support for `min` without `max` or `max` without `min`
-// TODO: Add support for string fields
 // TODO: Write .quops schema definition ($schema)
 
 pub mod bit;
 pub mod traits;
 mod errors;
 
-pub use bit::{BitReader, BitWriter};
+pub use bit::{BitOutput, BitReader, BitWriter};
 pub use quops_derive::{Decode, Encode};
 pub use errors::{DecodeError, EncodeError};
 
@@ -18,6 +15,21 @@ pub fn encode<T: traits::Encode>(value: &T) -> Result<Vec<u8>, EncodeError> {
     traits::Encode::encode(value)
 }
 
+#[inline(always)]
+pub fn encode_into<T: traits::Encode>(value: &T, out: &mut Vec<u8>) -> Result<(), EncodeError> {
+    traits::Encode::encode_into(value, out)
+}
+
+#[inline(always)]
+pub fn encode_to_slice<T: traits::Encode>(value: &T, out: &mut [u8]) -> Result<usize, EncodeError> {
+    traits::Encode::encode_to_slice(value, out)
+}
+
+#[inline(always)]
+pub fn encode_with<T: traits::Encode, O: BitOutput>(value: &T, writer: &mut BitWriter<O>) -> Result<usize, EncodeError> {
+    traits::Encode::encode_with(value, writer)
+}
+
 #[inline(always)]
 pub fn decode<T: traits::Decode>(buffer: &[u8]) -> Result<T, DecodeError> {
     traits::Decode::decode(buffer)
@@ -1,13 +1,102 @@
+use crate::bit::{BitOutput, BitReader, BitWriter};
 use crate::errors::{EncodeError, DecodeError};
 
-pub trait Encode {
-    fn encode(&self) -> Result<Vec<u8>, EncodeError>;
+/// Bit-level encoding of a value as part of a larger message.
+///
+/// Derived records call this for every nested record and enum field, so types implementing it
+/// by hand can be mixed freely with derived ones.
+pub trait EncodeBits {
+    fn encode_bits<O: BitOutput>(&self, writer: &mut BitWriter<O>) -> Result<(), EncodeError>;
 }
 
-pub trait Decode: Sized {
-    fn decode(bytes: &[u8]) -> Result<Self, DecodeError>;
+/// Bit-level decoding of a value as part of a larger message, the counterpart of [`EncodeBits`].
+pub trait DecodeBits: Sized {
+    fn decode_bits(reader: &mut BitReader) -> Result<Self, DecodeError>;
 }
 
-pub trait AsU64 {
-    fn as_u64(&self) -> Result<u64, EncodeError>;
+/// Boxes encode as their contents, which is what allows schemas to refer to themselves.
+impl<T: EncodeBits> EncodeBits for Box<T> {
+    #[inline(always)]
+    fn encode_bits<O: BitOutput>(&self, writer: &mut BitWriter<O>) -> Result<(), EncodeError> {
+        T::encode_bits(self, writer)
+    }
+}
+
+impl<T: DecodeBits> DecodeBits for Box<T> {
+    #[inline(always)]
+    fn decode_bits(reader: &mut BitReader) -> Result<Self, DecodeError> {
+        T::decode_bits(reader).map(Box::new)
+    }
+}
+
+pub trait Encode: EncodeBits {
+    /// Number of bytes worth reserving up front before encoding.
+    #[inline(always)]
+    fn size_hint(&self) -> usize {
+        0
+    }
+
+    #[inline(always)]
+    fn encode(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut out = Vec::with_capacity(self.size_hint());
+        self.encode_into(&mut out)?;
+        Ok(out)
+    }
+
+    /// Appends the encoded message to `out`. On error, `out` is left as it was.
+    ///
+    /// Every call creates a new writer, which allocates to queue `bytes` and `string` fields.
+    /// Use [`Encode::encode_with`] to reuse one writer across messages instead.
+    #[inline(always)]
+    fn encode_into(&self, out: &mut Vec<u8>) -> Result<(), EncodeError> {
+        let start = out.len();
+        out.reserve(self.size_hint());
+        let mut writer = BitWriter::new(&mut *out);
+        let result = self.encode_bits(&mut writer).and_then(|()| Ok(writer.finish()?));
+        if let Err(err) = result {
+            out.truncate(start);
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Writes the encoded message to the start of `out` and returns its length in bytes.
+    ///
+    /// Like [`Encode::encode_into`], this allocates for `bytes` and `string` fields.
+    #[inline(always)]
+    fn encode_to_slice(&self, out: &mut [u8]) -> Result<usize, EncodeError> {
+        let mut writer = BitWriter::new(out);
+        self.encode_bits(&mut writer)?;
+        Ok(writer.finish()?)
+    }
+
+    /// Resets `writer` and encodes the message into it, returning its length in bytes.
+    ///
+    /// The writer keeps its buffers between calls, so encoding many messages through one writer
+    /// does not allocate once they have grown large enough. The message is available from
+    /// [`BitWriter::written`] until the next call.
+    #[inline(always)]
+    fn encode_with<O: BitOutput>(&self, writer: &mut BitWriter<O>) -> Result<usize, EncodeError> {
+        writer.reset();
+        self.encode_bits(writer)?;
+        Ok(writer.finish()?)
+    }
+}
+
+pub trait Decode: DecodeBits {
+    #[inline(always)]
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = BitReader::new(bytes);
+        Self::decode_bits(&mut reader)
+    }
+}
+
+/// Maps a schema enum variant to the discriminant written on the wire.
+pub trait ToDiscriminant {
+    fn to_discriminant(&self) -> u64;
+}
+
+/// Maps a discriminant read from the wire back to a schema enum variant.
+pub trait FromDiscriminant: Sized {
+    fn from_discriminant(discriminant: u64) -> Result<Self, DecodeError>;
 }
@@ -0,0 +1,42 @@
+mod common;
+
+use common::round_trip;
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Fixed.quops")]
+struct Fixed {
+    hash: [u8; 4],
+    names: [String; 2],
+    grid: [[u8; 2]; 3],
+    flags: Option<[bool; 2]>,
+}
+
+fn fixed() -> Fixed {
+    Fixed {
+        hash: [0xde, 0xad, 0xbe, 0xef],
+        names: ["ab".to_string(), String::new()],
+        grid: [[0, 1], [2, 3], [6, 7]],
+        flags: Some([true, false]),
+    }
+}
+
+#[test]
+fn fixed_length_fields_round_trip() {
+    round_trip(&fixed());
+    round_trip(&Fixed { flags: None, ..fixed() });
+}
+
+#[test]
+fn fixed_length_fields_have_no_length_prefix() {
+    // 18 bits of grid, two 5-bit string lengths and 3 bits of flags, followed by the 4 bytes of the hash.
+    let bytes = round_trip(&Fixed { names: [String::new(), String::new()], ..fixed() });
+    assert_eq!(bytes.len(), 4 + 4);
+}
+
+#[test]
+fn errors_in_fixed_length_arrays_are_reported() {
+    let mut bytes = quops::encode(&fixed()).unwrap();
+    let first_name = bytes.len() - 4 - 1;
+    bytes[first_name] = 0xff;
+    assert!(matches!(quops::decode::<Fixed>(&bytes), Err(quops::DecodeError::InvalidUtf8(_))));
+}
@@ -0,0 +1,23 @@
+use quops::BitReader;
+
+#[derive(Debug, PartialEq, quops::Decode)]
+#[schema(path = "./schemas/Language.quops")]
+enum Language {
+    English,
+    French,
+}
+
+#[test]
+fn reads_one_byte_input_without_reading_past_it() {
+    let bytes: Box<[u8]> = Box::new([0b1010_0101]);
+    let mut reader = BitReader::new(&bytes);
+    assert_eq!(reader.read(3).unwrap(), 0b101);
+    assert_eq!(reader.read(5).unwrap(), 0b10100);
+    assert!(reader.read(1).is_err());
+}
+
+#[test]
+fn decodes_one_byte_message_from_exactly_sized_slice() {
+    let bytes: Box<[u8]> = Box::new([1]);
+    assert_eq!(quops::decode::<Language>(&bytes).unwrap(), Language::French);
+}
@@ -0,0 +1,10 @@
+use std::fmt::Debug;
+
+use quops::traits::{Decode, Encode};
+
+/// Encodes `value`, checks that it decodes back unchanged and returns the encoded bytes.
+pub fn round_trip<T: Encode + Decode + PartialEq + Debug>(value: &T) -> Vec<u8> {
+    let bytes = quops::encode(value).unwrap();
+    assert_eq!(&quops::decode::<T>(&bytes).unwrap(), value);
+    bytes
+}
@@ -0,0 +1,6 @@
+#[test]
+fn schema_errors_fail_to_compile() {
+    // The UI tests are built in a separate crate, so schema paths are resolved against this one instead.
+    std::env::set_var("QUOPS_SCHEMA_ROOT", env!("CARGO_MANIFEST_DIR"));
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
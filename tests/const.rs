@@ -0,0 +1,32 @@
+mod common;
+
+use common::round_trip;
+
+#[derive(Debug, Default, PartialEq)]
+struct Kind;
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Versioned.quops")]
+struct Versioned {
+    kind: Kind,
+    version: (),
+    n: u8,
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/VersionedV3.quops")]
+struct VersionedV3 {
+    n: u8,
+}
+
+#[test]
+fn only_checked_consts_take_bits() {
+    let bytes = round_trip(&Versioned { kind: Kind, version: (), n: 7 });
+    assert_eq!(bytes, [42, 7]);
+}
+
+#[test]
+fn checked_consts_reject_other_values() {
+    let bytes = quops::encode(&VersionedV3 { n: 7 }).unwrap();
+    assert!(quops::decode::<Versioned>(&bytes).is_err());
+}
@@ -0,0 +1,55 @@
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/InnerV1.quops")]
+struct InnerV1 {
+    a: u8,
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/InnerV2.quops")]
+struct InnerV2 {
+    a: u8,
+    b: u8,
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/OuterV1.quops")]
+struct OuterV1 {
+    inner: InnerV1,
+    flag: bool,
+    n: u8,
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/OuterV2.quops")]
+struct OuterV2 {
+    inner: InnerV1,
+    flag: bool,
+    n: u8,
+    m: u8,
+}
+
+#[test]
+fn fields_appended_to_the_message_take_their_default() {
+    let bytes = quops::encode(&InnerV1 { a: 100 }).unwrap();
+    assert_eq!(quops::decode::<InnerV2>(&bytes).unwrap(), InnerV2 { a: 100, b: 5 });
+}
+
+#[test]
+fn fields_appended_after_a_nested_record_take_their_default() {
+    let bytes = quops::encode(&OuterV1 { inner: InnerV1 { a: 1 }, flag: true, n: 3 }).unwrap();
+    let value = quops::decode::<OuterV2>(&bytes).unwrap();
+    assert_eq!(value, OuterV2 { inner: InnerV1 { a: 1 }, flag: true, n: 3, m: 9 });
+}
+
+#[test]
+fn fields_with_defaults_round_trip() {
+    for (a, b) in [(1, 2), (1, 5)] {
+        let value = InnerV2 { a, b };
+        let bytes = quops::encode(&value).unwrap();
+        assert_eq!(quops::decode::<InnerV2>(&bytes).unwrap(), value);
+    }
+
+    let value = OuterV2 { inner: InnerV1 { a: 1 }, flag: false, n: 3, m: 4 };
+    let bytes = quops::encode(&value).unwrap();
+    assert_eq!(quops::decode::<OuterV2>(&bytes).unwrap(), value);
+}
@@ -0,0 +1,38 @@
+use quops::BitWriter;
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./schemas/ChatMessage.quops")]
+struct ChatMessage {
+    player_id: u64,
+    message: Vec<u8>,
+    asd: Vec<i32>,
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Position.quops")]
+struct Position {
+    x: f64,
+    label: String,
+}
+
+#[test]
+fn reused_writer_encodes_like_a_fresh_one() {
+    let mut writer = BitWriter::new(Vec::new());
+    for player_id in [7, 1000] {
+        let message = ChatMessage { player_id, message: format!("hello {}", player_id).into_bytes(), asd: vec![1, 2, 3] };
+        let len = quops::encode_with(&message, &mut writer).unwrap();
+        assert_eq!(writer.written(), quops::encode(&message).unwrap());
+        assert_eq!(len, writer.written().len());
+        assert_eq!(quops::decode::<ChatMessage>(writer.written()).unwrap(), message);
+    }
+}
+
+#[test]
+fn failed_encode_leaves_the_output_unchanged() {
+    let mut out = vec![9];
+    assert!(quops::encode_into(&Position { x: 500.0, label: "far".to_string() }, &mut out).is_err());
+    assert_eq!(out, [9]);
+
+    quops::encode_into(&Position { x: 1.5, label: "near".to_string() }, &mut out).unwrap();
+    assert_eq!(quops::decode::<Position>(&out[1..]).unwrap(), Position { x: 1.5, label: "near".to_string() });
+}
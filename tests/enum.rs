@@ -0,0 +1,60 @@
+mod common;
+
+use common::round_trip;
+use quops::traits::{FromDiscriminant, ToDiscriminant};
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Status.quops")]
+enum Status {
+    Offline,
+    Online,
+    Away,
+    Banned,
+}
+
+#[test]
+fn enums_use_their_schema_ids() {
+    assert_eq!(Status::Offline.to_discriminant(), 0);
+    assert_eq!(Status::Online.to_discriminant(), 10);
+    assert_eq!(Status::Away.to_discriminant(), 11);
+    assert_eq!(Status::Banned.to_discriminant(), 1000);
+    assert_eq!(Status::from_discriminant(11).unwrap(), Status::Away);
+}
+
+#[test]
+fn enums_round_trip() {
+    for status in [Status::Offline, Status::Online, Status::Away, Status::Banned] {
+        // Ids up to 1000 take 10 bits.
+        assert_eq!(round_trip(&status).len(), 2);
+    }
+}
+
+#[test]
+fn unassigned_ids_are_rejected() {
+    assert!(Status::from_discriminant(1).is_err());
+    assert!(quops::decode::<Status>(&[1, 0]).is_err());
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/RoleV2.quops")]
+enum RoleV2 {
+    Player,
+    Leader,
+    Spectator,
+    Other(u64),
+}
+
+#[test]
+fn unknown_ids_decode_into_the_unknown_variant() {
+    for role in [RoleV2::Player, RoleV2::Spectator, RoleV2::Other(15)] {
+        round_trip(&role);
+    }
+    assert!(quops::encode(&RoleV2::Other(16)).is_err());
+}
+
+#[test]
+fn unknown_variants_holding_known_ids_encode_as_the_known_variant() {
+    let bytes = quops::encode(&RoleV2::Other(1)).unwrap();
+    assert_eq!(bytes, quops::encode(&RoleV2::Leader).unwrap());
+    assert_eq!(quops::decode::<RoleV2>(&bytes).unwrap(), RoleV2::Leader);
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use common::round_trip;
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Floats.quops")]
+struct Floats {
+    single: f32,
+    double: f64,
+    optional: Option<f64>,
+}
+
+#[test]
+fn floats_round_trip() {
+    round_trip(&Floats { single: 1.5, double: -0.1, optional: Some(f64::MAX) });
+    round_trip(&Floats { single: f32::INFINITY, double: f64::MIN_POSITIVE, optional: None });
+}
+
+#[test]
+fn floats_keep_their_bits() {
+    let bytes = quops::encode(&Floats { single: f32::NAN, double: -0.0, optional: None }).unwrap();
+    let value = quops::decode::<Floats>(&bytes).unwrap();
+    assert_eq!(value.single.to_bits(), f32::NAN.to_bits());
+    assert_eq!(value.double.to_bits(), (-0.0f64).to_bits());
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Quantized.quops")]
+struct Quantized {
+    angle: f32,
+    volume: f64,
+}
+
+#[test]
+fn quantized_floats_round_to_their_precision() {
+    round_trip(&Quantized { angle: 0.0, volume: 1.0 });
+    round_trip(&Quantized { angle: 359.5, volume: 0.25 });
+
+    let bytes = quops::encode(&Quantized { angle: 90.2, volume: 0.123 }).unwrap();
+    let value = quops::decode::<Quantized>(&bytes).unwrap();
+    assert_eq!(value.angle, 90.0);
+    assert!((value.volume - 0.12).abs() < 1e-9);
+}
+
+#[test]
+fn quantized_floats_clamp_or_reject_values_out_of_range() {
+    assert!(quops::encode(&Quantized { angle: 361.0, volume: 0.0 }).is_err());
+
+    let bytes = quops::encode(&Quantized { angle: 0.0, volume: 2.0 }).unwrap();
+    assert_eq!(quops::decode::<Quantized>(&bytes).unwrap().volume, 1.0);
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use common::round_trip;
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Profile.quops", name = "HomeAddress")]
+struct HomeAddress {
+    city: String,
+    zip: u32,
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Profile.quops", name = "Theme")]
+enum Theme {
+    Light,
+    Dark,
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Profile.quops", name = "Badge")]
+enum Badge {
+    Gold,
+    Silver,
+    Bronze,
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Profile.quops")]
+struct Profile {
+    home_address: HomeAddress,
+    theme: Option<Theme>,
+    badges: Vec<Badge>,
+}
+
+#[test]
+fn inline_types_round_trip() {
+    let home_address = HomeAddress { city: "Oslo".to_string(), zip: 150 };
+    round_trip(&home_address);
+    round_trip(&Badge::Bronze);
+    round_trip(&Profile { home_address, theme: Some(Theme::Dark), badges: vec![Badge::Gold, Badge::Bronze] });
+    round_trip(&Profile { home_address: HomeAddress { city: String::new(), zip: 99999 }, theme: None, badges: Vec::new() });
+}
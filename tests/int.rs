@@ -0,0 +1,67 @@
+mod common;
+
+use common::round_trip;
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Unbounded.quops")]
+struct Unbounded {
+    small: i32,
+    large: Option<i64>,
+}
+
+#[test]
+fn unbounded_ints_round_trip() {
+    for small in [0, 1, -1, i32::MIN, i32::MAX] {
+        for large in [None, Some(0), Some(i64::MIN), Some(i64::MAX)] {
+            round_trip(&Unbounded { small, large });
+        }
+    }
+}
+
+#[test]
+fn small_negative_unbounded_ints_stay_small() {
+    let bytes = round_trip(&Unbounded { small: -1, large: None });
+    assert_eq!(bytes.len(), 1);
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/HalfBounded.quops")]
+struct HalfBounded {
+    above: i64,
+    below: i64,
+}
+
+#[test]
+fn half_bounded_ints_round_trip() {
+    for (above, below) in [(-10, 100), (0, 0), (i64::MAX, i64::MIN + 100)] {
+        round_trip(&HalfBounded { above, below });
+    }
+}
+
+#[test]
+fn half_bounded_ints_reject_values_past_their_bound() {
+    assert!(quops::encode(&HalfBounded { above: -11, below: 0 }).is_err());
+    assert!(quops::encode(&HalfBounded { above: 0, below: 101 }).is_err());
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Wide.quops")]
+struct Wide {
+    byte: u8,
+    unsigned: u64,
+    signed: i64,
+    optional: Option<u64>,
+}
+
+#[test]
+fn sixty_four_bit_ranges_round_trip() {
+    round_trip(&Wide { unsigned: u64::MAX, signed: i64::MIN, optional: Some(u64::MAX), byte: 255 });
+    round_trip(&Wide { unsigned: 0, signed: i64::MAX, optional: None, byte: 0 });
+}
+
+#[test]
+fn bounded_ints_take_the_width_of_their_span() {
+    // `byte` fills exactly the first byte, so `unsigned` starts on the second.
+    let bytes = round_trip(&Wide { unsigned: 1, signed: -1, optional: None, byte: 255 });
+    assert_eq!(bytes[..2], [255, 1]);
+}
@@ -0,0 +1,37 @@
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Scores4.quops")]
+struct Scores4 {
+    scores: HashMap<u8, String>,
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Scores7.quops")]
+struct Scores7 {
+    scores: BTreeMap<u8, String>,
+}
+
+fn scores(count: u8) -> impl Iterator<Item = (u8, String)> {
+    (0..count).map(|key| (key, format!("player {}", key)))
+}
+
+#[test]
+fn maps_round_trip() {
+    let value = Scores4 { scores: scores(4).collect() };
+    let bytes = quops::encode(&value).unwrap();
+    assert_eq!(quops::decode::<Scores4>(&bytes).unwrap(), value);
+
+    let value = Scores7 { scores: scores(7).collect() };
+    let bytes = quops::encode(&value).unwrap();
+    assert_eq!(quops::decode::<Scores7>(&bytes).unwrap(), value);
+}
+
+#[test]
+fn maps_longer_than_max_length_are_rejected() {
+    assert!(matches!(quops::encode(&Scores4 { scores: scores(5).collect() }), Err(quops::EncodeError::OutOfBounds(_))));
+
+    // Both limits share a 3-bit length prefix, so the longer map is only caught by the check itself.
+    let bytes = quops::encode(&Scores7 { scores: scores(5).collect() }).unwrap();
+    assert!(matches!(quops::decode::<Scores4>(&bytes), Err(quops::DecodeError::OutOfBounds(_))));
+}
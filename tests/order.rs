@@ -0,0 +1,27 @@
+mod common;
+
+use common::round_trip;
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Declared.quops")]
+struct Declared {
+    a: u8,
+    b: u8,
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Indexed.quops")]
+struct Indexed {
+    a: u8,
+    b: u8,
+}
+
+#[test]
+fn fields_are_written_in_declared_order() {
+    assert_eq!(round_trip(&Declared { a: 1, b: 2 }), [0x12]);
+}
+
+#[test]
+fn fields_with_an_index_are_written_in_index_order() {
+    assert_eq!(round_trip(&Indexed { a: 1, b: 2 }), [0x21]);
+}
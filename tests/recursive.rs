@@ -0,0 +1,40 @@
+mod common;
+
+use common::round_trip;
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Comment.quops")]
+struct Comment {
+    text: String,
+    parent: Option<Box<Comment>>,
+    replies: Vec<Comment>,
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Expr.quops")]
+enum Expr {
+    Literal(i64),
+    Neg(Box<Expr>),
+    Add { left: Box<Expr>, right: Box<Expr> },
+}
+
+fn comment(text: &str, replies: Vec<Comment>) -> Comment {
+    Comment { text: text.to_string(), parent: None, replies }
+}
+
+#[test]
+fn recursive_records_round_trip() {
+    round_trip(&comment("leaf", Vec::new()));
+    round_trip(&Comment {
+        text: "reply".to_string(),
+        parent: Some(Box::new(comment("root", Vec::new()))),
+        replies: vec![comment("a", vec![comment("b", Vec::new())]), comment("c", Vec::new())],
+    });
+}
+
+#[test]
+fn recursive_unions_round_trip() {
+    let literal = |n| Box::new(Expr::Literal(n));
+    round_trip(&Expr::Literal(-3));
+    round_trip(&Expr::Add { left: Box::new(Expr::Neg(literal(1))), right: Box::new(Expr::Add { left: literal(2), right: literal(3) }) });
+}
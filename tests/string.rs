@@ -0,0 +1,30 @@
+mod common;
+
+use common::round_trip;
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Strings.quops")]
+struct Strings {
+    name: String,
+    bio: String,
+    nickname: Option<String>,
+}
+
+#[test]
+fn strings_round_trip() {
+    round_trip(&Strings { name: String::new(), bio: String::new(), nickname: None });
+    round_trip(&Strings { name: "Zoë".to_string(), bio: "ü".repeat(15), nickname: Some("z".to_string()) });
+}
+
+#[test]
+fn strings_longer_than_max_length_are_rejected() {
+    assert!(quops::encode(&Strings { name: "123456789".to_string(), bio: String::new(), nickname: None }).is_err());
+}
+
+#[test]
+fn invalid_utf8_is_rejected() {
+    let mut bytes = quops::encode(&Strings { name: "ab".to_string(), bio: String::new(), nickname: None }).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] = 0xff;
+    assert!(matches!(quops::decode::<Strings>(&bytes), Err(quops::DecodeError::InvalidUtf8(_))));
+}
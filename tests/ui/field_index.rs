@@ -0,0 +1,15 @@
+#[derive(quops::Encode)]
+#[schema(path = "tests/schemas/MissingIndex.quops")]
+struct MissingIndex {
+    a: u8,
+    b: u8,
+}
+
+#[derive(quops::Encode)]
+#[schema(path = "tests/schemas/DuplicateIndex.quops")]
+struct DuplicateIndex {
+    a: u8,
+    b: u8,
+}
+
+fn main() {}
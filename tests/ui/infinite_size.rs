@@ -0,0 +1,8 @@
+#[derive(quops::Encode)]
+#[schema(path = "tests/schemas/Infinite.quops")]
+struct Infinite {
+    n: i64,
+    next: Box<Infinite>,
+}
+
+fn main() {}
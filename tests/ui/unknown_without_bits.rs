@@ -0,0 +1,18 @@
+#[derive(quops::Encode, quops::Decode)]
+#[schema(path = "tests/schemas/UnknownWithoutBits.quops")]
+enum UnknownWithoutBits {
+    Player,
+    Leader,
+    Other(u64),
+}
+
+#[derive(quops::Encode, quops::Decode)]
+#[schema(path = "tests/schemas/UnfixedWidth.quops")]
+enum UnfixedWidth {
+    Player,
+    Leader,
+    #[quops(unknown)]
+    Other(u64),
+}
+
+fn main() {}
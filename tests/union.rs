@@ -0,0 +1,50 @@
+mod common;
+
+use common::round_trip;
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Position.quops")]
+struct Position {
+    x: f64,
+    label: String,
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Event.quops")]
+enum Event {
+    Ping,
+    Score(u16),
+    Joined(Position),
+    Chat { writer: bool, value: String, bytes: Option<Vec<u8>> },
+}
+
+#[derive(Debug, PartialEq, quops::Encode, quops::Decode)]
+#[schema(path = "./tests/schemas/Log.quops")]
+struct Log {
+    first: Event,
+    last: Option<Event>,
+    rest: Vec<Event>,
+}
+
+fn events() -> Vec<Event> {
+    vec![
+        Event::Ping,
+        Event::Score(1000),
+        Event::Joined(Position { x: -2.5, label: "spawn".to_string() }),
+        Event::Chat { writer: true, value: "hi".to_string(), bytes: Some(vec![1, 2]) },
+        Event::Chat { writer: false, value: String::new(), bytes: None },
+    ]
+}
+
+#[test]
+fn unions_round_trip() {
+    for event in events() {
+        round_trip(&event);
+    }
+}
+
+#[test]
+fn unions_round_trip_as_fields() {
+    round_trip(&Log { first: Event::Ping, last: None, rest: Vec::new() });
+    round_trip(&Log { first: Event::Score(3), last: Some(Event::Ping), rest: events() });
+}